@@ -0,0 +1,729 @@
+use std::{env, time::Duration};
+
+use dbus::{
+    arg::messageitem::MessageItem,
+    blocking::Connection,
+    channel::{BusType, Channel},
+    message::MatchRule,
+    strings::{BusName, Interface, Member, Path},
+    Message,
+};
+use log::{debug, warn};
+
+use crate::{
+    dbus_address::DBusAddress,
+    dbus_argument::DBusArgument,
+    dbus_error::DBusError,
+    dbus_type::DBusType,
+    dbus_value::DBusValue,
+    introspection::{parse_entries, Entry, Method, Object, Property},
+};
+
+/// Libdbus treats `i32::MAX` milliseconds as no timeout.
+pub const TIMEOUT_INFINITE: Duration = Duration::from_millis(i32::MAX as u64);
+
+/// Header flag of the message format, see the D-Bus specification.
+const ALLOW_INTERACTIVE_AUTHORIZATION: u8 = 0x4;
+
+/// How messages are sent by a [`Client`].
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// How long to wait for replies, [`TIMEOUT_INFINITE`] waits forever.
+    pub timeout: Duration,
+    /// Do not start the destination service if it is not running.
+    pub no_auto_start: bool,
+    /// Allow the service to ask the user for authorization, e.g. through polkit.
+    pub allow_interactive_auth: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            timeout: Duration::from_secs(25),
+            no_auto_start: false,
+            allow_interactive_auth: false,
+        }
+    }
+}
+
+/// A blocking connection to a bus.
+pub struct Client {
+    connection: Connection,
+    options: Options,
+}
+
+impl Client {
+    /// Connects to `address`, which is `session`, `system`, `starter` or a `;` separated list of
+    /// D-Bus addresses that are tried in order.
+    pub fn connect(address: &str, options: Options) -> Result<Client, DBusError> {
+        let connection = match address {
+            "session" => get_private(BusType::Session),
+            "system" => get_private(BusType::System),
+            "starter" => match env::var("DBUS_STARTER_ADDRESS") {
+                Ok(address) => open_connection(&address),
+                Err(_) => Err(DBusError::InvalidAddress(
+                    "DBUS_STARTER_ADDRESS is not set".into(),
+                )),
+            },
+            _ => open_connection(address),
+        }?;
+
+        Ok(Client {
+            connection,
+            options,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    pub fn list_names(&self) -> Result<Vec<String>, DBusError> {
+        let proxy = self
+            .connection
+            .with_proxy("org.freedesktop.DBus", "/", self.options.timeout);
+        let (names,): (Vec<String>,) =
+            proxy.method_call("org.freedesktop.DBus", "ListNames", ())?;
+
+        Ok(names)
+    }
+
+    /// Introspects the object at `path`.
+    pub fn introspect(&self, bus_name: &str, path: &str) -> Result<Vec<Entry>, DBusError> {
+        let message = new_method_call(
+            bus_name,
+            path,
+            "org.freedesktop.DBus.Introspectable",
+            "Introspect",
+        )?;
+
+        let capas: String = self
+            .send(message)?
+            .read1()
+            .map_err(|err| DBusError::Introspection(err.to_string()))?;
+
+        debug!("{:?}", capas);
+
+        parse_entries(&capas)
+    }
+
+    /// Recursively introspects the objects below `path`, at most `max_depth` levels deep.
+    ///
+    /// Failures below the root are reported in the tree, a failing root is an error.
+    pub fn tree(
+        &self,
+        bus_name: &str,
+        path: &str,
+        max_depth: Option<usize>,
+    ) -> Result<Object, DBusError> {
+        let entries = self.introspect(bus_name, path)?;
+
+        Ok(self.walk(bus_name, path, entries, 0, max_depth))
+    }
+
+    fn walk(
+        &self,
+        bus_name: &str,
+        path: &str,
+        entries: Vec<Entry>,
+        depth: usize,
+        max_depth: Option<usize>,
+    ) -> Object {
+        let mut object = Object {
+            path: path.to_string(),
+            interfaces: Vec::new(),
+            error: None,
+            children: Vec::new(),
+        };
+
+        let descend = max_depth.is_none_or(|max_depth| depth < max_depth);
+
+        for entry in entries {
+            match entry {
+                Entry::Interface { name, .. } => object.interfaces.push(name),
+                Entry::Node { name } if descend => {
+                    let child_path = format!("{}/{}", path.trim_end_matches('/'), name);
+
+                    object
+                        .children
+                        .push(match self.introspect(bus_name, &child_path) {
+                            Ok(entries) => {
+                                self.walk(bus_name, &child_path, entries, depth + 1, max_depth)
+                            }
+                            Err(err) => {
+                                warn!("Could not introspect {}: {}", child_path, err);
+
+                                Object {
+                                    path: child_path,
+                                    interfaces: Vec::new(),
+                                    error: Some(err),
+                                    children: Vec::new(),
+                                }
+                            }
+                        });
+                }
+                _ => {}
+            }
+        }
+
+        object
+    }
+
+    /// Looks up a method in the introspection data of the object at `path`.
+    pub fn find_method(
+        &self,
+        bus_name: &str,
+        path: &str,
+        interface_name: &str,
+        method_name: &str,
+    ) -> Result<Method, DBusError> {
+        let entries = self.introspect(bus_name, path)?;
+
+        let interface = entries.into_iter().find(|entry| {
+            if let Entry::Interface { name, .. } = entry {
+                name == interface_name
+            } else {
+                false
+            }
+        });
+
+        debug!("Found interface: {:?}\n", interface);
+
+        let methods = match interface {
+            Some(Entry::Interface { methods, .. }) => methods,
+            _ => {
+                return Err(DBusError::NotFound(format!(
+                    "No interface: {} on {} {}",
+                    interface_name, bus_name, path
+                )))
+            }
+        };
+
+        let method = methods
+            .into_iter()
+            .find(|method| method.name == method_name)
+            .ok_or_else(|| {
+                DBusError::NotFound(format!(
+                    "No method: {} on interface {}",
+                    method_name, interface_name
+                ))
+            })?;
+
+        debug!("Found method: {:?}\n", method);
+
+        Ok(method)
+    }
+
+    /// Looks up a property in the introspection data of the object at `path`.
+    pub fn find_property(
+        &self,
+        bus_name: &str,
+        path: &str,
+        interface_name: &str,
+        property_name: &str,
+    ) -> Result<Property, DBusError> {
+        let property = self
+            .introspect(bus_name, path)?
+            .into_iter()
+            .find_map(|entry| match entry {
+                Entry::Interface {
+                    name, properties, ..
+                } if name == interface_name => properties
+                    .into_iter()
+                    .find(|property| property.name == property_name),
+                _ => None,
+            });
+
+        debug!("Found property: {:?}\n", property);
+
+        property.ok_or_else(|| {
+            DBusError::NotFound(format!(
+                "No property: {} on interface {}",
+                property_name, interface_name
+            ))
+        })
+    }
+
+    /// Calls a method with `args`, which are a struct of all arguments or unit, and returns the
+    /// items of the reply.
+    pub fn call(
+        &self,
+        mut message: Message,
+        args: DBusArgument,
+    ) -> Result<Vec<MessageItem>, DBusError> {
+        append_arguments(&mut message, args)?;
+
+        Ok(self.send(message)?.get_items())
+    }
+
+    /// Calls a method without waiting for a reply.
+    pub fn call_no_reply(&self, mut message: Message, args: DBusArgument) -> Result<(), DBusError> {
+        append_arguments(&mut message, args)?;
+        message.set_no_reply(true);
+
+        self.send_no_reply(prepare(message, &self.options)?)
+    }
+
+    /// Emits a signal, `destination` restricts it to a single bus name.
+    pub fn emit(
+        &self,
+        path: &str,
+        interface_name: &str,
+        signal_name: &str,
+        destination: Option<&str>,
+        args: DBusArgument,
+    ) -> Result<(), DBusError> {
+        let mut message = new_signal(path, interface_name, signal_name, destination)?;
+
+        append_arguments(&mut message, args)?;
+
+        self.send_no_reply(message)
+    }
+
+    /// The value of a property, the variant of the reply is unwrapped.
+    pub fn get_property(
+        &self,
+        bus_name: &str,
+        path: &str,
+        interface_name: &str,
+        property_name: &str,
+    ) -> Result<Vec<MessageItem>, DBusError> {
+        let message = new_method_call(bus_name, path, "org.freedesktop.DBus.Properties", "Get")?
+            .append2(interface_name, property_name);
+
+        Ok(self
+            .send(message)?
+            .get_items()
+            .into_iter()
+            .map(|item| match item {
+                MessageItem::Variant(value) => *value,
+                item => item,
+            })
+            .collect())
+    }
+
+    pub fn set_property(
+        &self,
+        bus_name: &str,
+        path: &str,
+        interface_name: &str,
+        property_name: &str,
+        value: DBusArgument,
+    ) -> Result<(), DBusError> {
+        let mut message =
+            new_method_call(bus_name, path, "org.freedesktop.DBus.Properties", "Set")?
+                .append2(interface_name, property_name);
+
+        if let Some(item) = Option::<MessageItem>::try_from(value.validate()?)? {
+            message.append_items(&[MessageItem::Variant(Box::new(item))]);
+        }
+
+        self.send(message)?;
+
+        Ok(())
+    }
+
+    /// The reply of `org.freedesktop.DBus.Properties.GetAll`, a single `a{sv}`.
+    pub fn get_all_properties(
+        &self,
+        bus_name: &str,
+        path: &str,
+        interface_name: &str,
+    ) -> Result<Vec<MessageItem>, DBusError> {
+        let message = new_method_call(bus_name, path, "org.freedesktop.DBus.Properties", "GetAll")?
+            .append1(interface_name);
+
+        Ok(self.send(message)?.get_items())
+    }
+
+    /// Turns the connection into a monitor for `rules` and passes every message matching one of
+    /// them to `on_message` until the connection is lost.
+    pub fn monitor(
+        &self,
+        rules: Vec<MatchRule<'static>>,
+        mut on_message: impl FnMut(Message),
+    ) -> Result<(), DBusError> {
+        let proxy = self.connection.with_proxy(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            self.options.timeout,
+        );
+
+        let match_strings = rules
+            .iter()
+            .map(|rule| rule.match_str())
+            .collect::<Vec<_>>();
+
+        debug!("Match rules: {:?}", match_strings);
+
+        let monitoring: Result<(), dbus::Error> = proxy.method_call(
+            "org.freedesktop.DBus.Monitoring",
+            "BecomeMonitor",
+            (match_strings, 0u32),
+        );
+
+        if let Err(err) = monitoring {
+            warn!(
+                "BecomeMonitor failed, falling back to eavesdropping: {:?}",
+                err
+            );
+
+            for mut rule in rules.iter().cloned() {
+                rule.eavesdrop = true;
+                self.connection.add_match_no_cb(&rule.match_str())?;
+            }
+        }
+
+        loop {
+            if let Some(message) = self
+                .connection
+                .channel()
+                .blocking_pop_message(Duration::from_secs(1))
+                .map_err(|err| {
+                    DBusError::Connection(err.message().unwrap_or("Connection lost").to_string())
+                })?
+            {
+                // Messages queued before the rules took effect, like NameAcquired, reach us too.
+                if rules.iter().any(|rule| rule.matches(&message)) {
+                    on_message(message);
+                }
+            }
+        }
+    }
+
+    /// Sends a method call and waits for the reply.
+    pub fn send(&self, message: Message) -> Result<Message, DBusError> {
+        Ok(self
+            .connection
+            .channel()
+            .send_with_reply_and_block(prepare(message, &self.options)?, self.options.timeout)?)
+    }
+
+    fn send_no_reply(&self, message: Message) -> Result<(), DBusError> {
+        self.connection
+            .channel()
+            .send(message)
+            .map_err(|_| DBusError::Connection("Could not send message".into()))?;
+        self.connection.channel().flush();
+
+        Ok(())
+    }
+}
+
+/// Applies the header flags of `options` to a method call.
+///
+/// dbus 0.9 has no setter for `ALLOW_INTERACTIVE_AUTHORIZATION` and does not expose the libdbus
+/// message to call it through `dbus::ffi`, so the flag is set in the marshalled message, which can
+/// not carry file descriptors.
+fn prepare(mut message: Message, options: &Options) -> Result<Message, DBusError> {
+    message.set_auto_start(!options.no_auto_start);
+
+    if !options.allow_interactive_auth {
+        return Ok(message);
+    }
+
+    if message.get_items().iter().any(contains_fds) {
+        return Err(DBusError::Unsupported(
+            "--allow-interactive-auth can not be combined with file descriptor arguments".into(),
+        ));
+    }
+
+    // Marshalling requires a serial, it is reset below so the connection assigns its next one.
+    message.set_serial(u32::MAX);
+
+    let mut bytes = Vec::new();
+    message
+        .marshal(|chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<(), ()>(())
+        })
+        .expect("Marshalling into memory never fails");
+    bytes[2] |= ALLOW_INTERACTIVE_AUTHORIZATION;
+
+    let mut message = Message::demarshal(&bytes)?;
+
+    message.set_serial(0);
+
+    Ok(message)
+}
+
+/// Whether `item` is or contains a file descriptor, also inside variants.
+fn contains_fds(item: &MessageItem) -> bool {
+    match item {
+        MessageItem::UnixFd(_) => true,
+        MessageItem::Variant(value) => contains_fds(value),
+        MessageItem::Struct(items) => items.iter().any(contains_fds),
+        MessageItem::Array(items) => items.iter().any(contains_fds),
+        MessageItem::Dict(entries) => entries
+            .iter()
+            .any(|(key, value)| contains_fds(key) || contains_fds(value)),
+        _ => false,
+    }
+}
+
+pub fn new_method_call(
+    bus_name: &str,
+    path: &str,
+    interface_name: &str,
+    method_name: &str,
+) -> Result<Message, DBusError> {
+    Message::new_method_call(
+        BusName::new(bus_name).map_err(DBusError::InvalidName)?,
+        Path::new(path).map_err(DBusError::InvalidName)?,
+        Interface::new(interface_name).map_err(DBusError::InvalidName)?,
+        Member::new(method_name).map_err(DBusError::InvalidName)?,
+    )
+    .map_err(DBusError::InvalidName)
+}
+
+/// A signal, `destination` restricts it to a single bus name.
+pub fn new_signal(
+    path: &str,
+    interface_name: &str,
+    signal_name: &str,
+    destination: Option<&str>,
+) -> Result<Message, DBusError> {
+    let mut message =
+        Message::new_signal(path, interface_name, signal_name).map_err(DBusError::InvalidName)?;
+
+    if let Some(destination) = destination {
+        message.set_destination(Some(
+            BusName::new(destination.to_string()).map_err(DBusError::InvalidName)?,
+        ));
+    }
+
+    Ok(message)
+}
+
+/// The type of an argument list, arguments are passed as a struct literal.
+pub fn arguments_type(signature: &str) -> Result<DBusType, DBusError> {
+    let types = DBusType::parse(signature)?;
+
+    if types.is_empty() {
+        Ok(DBusType::Unit)
+    } else {
+        Ok(DBusType::Struct(types))
+    }
+}
+
+fn append_arguments(message: &mut Message, args: DBusArgument) -> Result<(), DBusError> {
+    let args = args.validate_arguments()?;
+
+    if let (DBusType::Struct(types), DBusValue::Vec(values)) = (args.dbus_type, args.dbus_value) {
+        for (index, (dbus_type, dbus_value)) in types.iter().zip(values).enumerate() {
+            let argument = DBusArgument {
+                dbus_type,
+                dbus_value,
+            };
+
+            if let Some(item) = argument.to_message_item(&format!("arg{}", index))? {
+                message.append_items(&[item]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn get_private(bus_type: BusType) -> Result<Connection, DBusError> {
+    Channel::get_private(bus_type)
+        .map(Connection::from)
+        .map_err(|err| {
+            DBusError::Connection(format!(
+                "{:?} bus ({})",
+                bus_type,
+                err.message().unwrap_or("Unknown error")
+            ))
+        })
+}
+
+fn open_connection(address: &str) -> Result<Connection, DBusError> {
+    let mut errors = Vec::new();
+
+    for address in DBusAddress::parse_list(address)? {
+        debug!("Connecting to: {}", address);
+
+        match Channel::open_private(&address.to_string()).and_then(|mut channel| {
+            channel.register()?;
+            Ok(channel)
+        }) {
+            Ok(channel) => return Ok(Connection::from(channel)),
+            Err(err) => {
+                warn!("Could not connect to {}: {:?}", address, err);
+                errors.push(format!(
+                    "{} ({})",
+                    address,
+                    err.message().unwrap_or("Unknown error")
+                ));
+            }
+        }
+    }
+
+    Err(DBusError::Connection(errors.join(", ")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, os::unix::io::IntoRawFd};
+
+    use dbus::{
+        arg::{messageitem::MessageItem, OwnedFd},
+        Message, MessageType,
+    };
+
+    use crate::{
+        client::{append_arguments, arguments_type, new_method_call, new_signal, prepare, Options},
+        dbus_argument::DBusArgument,
+        dbus_error::DBusError,
+        dbus_type::DBusType,
+        dbus_value::DBusValue,
+    };
+
+    /// The flags byte of the marshalled header.
+    fn flags(message: &mut Message) -> u8 {
+        let mut bytes = Vec::new();
+
+        message.set_serial(1);
+        message
+            .marshal(|chunk| {
+                bytes.extend_from_slice(chunk);
+                Ok::<(), ()>(())
+            })
+            .unwrap();
+
+        bytes[2]
+    }
+
+    #[test]
+    fn test_prepare() {
+        let call = || new_method_call("org.test", "/org/test", "org.test.Calls", "Ping").unwrap();
+        let prepared = |message, no_auto_start, allow_interactive_auth| {
+            prepare(
+                message,
+                &Options {
+                    no_auto_start,
+                    allow_interactive_auth,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        assert_eq!(flags(&mut prepared(call(), false, false)), 0x0);
+        assert_eq!(flags(&mut prepared(call(), true, false)), 0x2);
+        assert_eq!(flags(&mut prepared(call(), false, true)), 0x4);
+        assert_eq!(flags(&mut prepared(call(), true, true)), 0x6);
+        // Without a serial the connection numbers each call on its own.
+        assert_eq!(prepared(call(), false, true).get_serial(), None);
+
+        let mut message = call().append1("argument");
+
+        message.set_no_reply(true);
+
+        let mut message = prepared(message, true, true);
+
+        assert_eq!(flags(&mut message), 0x7);
+        assert_eq!(
+            message.get_items(),
+            vec![MessageItem::Str("argument".into())]
+        );
+
+        // File descriptors are lost when marshalling, even inside variants.
+        let fd = File::open("/dev/null").unwrap().into_raw_fd();
+        let mut message = call();
+
+        message.append_items(&[MessageItem::Variant(Box::new(MessageItem::UnixFd(
+            unsafe { OwnedFd::new(fd) },
+        )))]);
+
+        assert!(matches!(
+            prepare(
+                message,
+                &Options {
+                    allow_interactive_auth: true,
+                    ..Default::default()
+                }
+            ),
+            Err(DBusError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_signal() {
+        let mut message =
+            new_signal("/org/test", "org.test.Signals", "Changed", Some(":1.42")).unwrap();
+        let dbus_type = DBusType::try_from("(su)").unwrap();
+        let dbus_value = DBusValue::parse("(count, 8)", &dbus_type).unwrap();
+
+        append_arguments(
+            &mut message,
+            DBusArgument {
+                dbus_type: &dbus_type,
+                dbus_value: &dbus_value,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(message.msg_type(), MessageType::Signal);
+        assert_eq!(message.path().unwrap().to_string(), "/org/test");
+        assert_eq!(message.interface().unwrap().to_string(), "org.test.Signals");
+        assert_eq!(message.member().unwrap().to_string(), "Changed");
+        assert_eq!(message.destination().unwrap().to_string(), ":1.42");
+        assert_eq!(
+            message.get_items(),
+            vec![MessageItem::Str("count".into()), MessageItem::UInt32(8)]
+        );
+        assert!(new_signal("/org/test", "org.test.Signals", "Changed", None)
+            .unwrap()
+            .destination()
+            .is_none());
+        assert!(matches!(
+            new_signal("org/test", "org.test.Signals", "Changed", None),
+            Err(DBusError::InvalidName(_))
+        ));
+        assert!(matches!(
+            new_signal(
+                "/org/test",
+                "org.test.Signals",
+                "Changed",
+                Some("no bus name")
+            ),
+            Err(DBusError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_arguments_type() {
+        assert_eq!(arguments_type(""), Ok(DBusType::Unit));
+        assert_eq!(
+            arguments_type("sa{sv}"),
+            Ok(DBusType::Struct(vec![
+                DBusType::String,
+                DBusType::try_from("a{sv}").unwrap()
+            ]))
+        );
+        assert!(matches!(
+            arguments_type("a{vs}"),
+            Err(DBusError::InvalidSignature { .. })
+        ));
+
+        // call-raw and emit parse all arguments as one struct literal.
+        let dbus_type = arguments_type("su").unwrap();
+
+        assert_eq!(
+            DBusValue::parse("(org.test, 8)", &dbus_type),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::String("org.test".into()),
+                DBusValue::UInt32(8)
+            ]))
+        );
+        assert_eq!(
+            DBusValue::parse("", &arguments_type("").unwrap()),
+            Ok(DBusValue::Unit)
+        );
+    }
+}
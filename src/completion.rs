@@ -0,0 +1,350 @@
+use std::{
+    env,
+    io::{self, Write},
+    time::Duration,
+};
+
+use clap::{App, AppSettings, Arg, Shell, SubCommand};
+use dbus_client::{client::Options, introspection::Entry, Client};
+use itertools::Itertools;
+
+use crate::app;
+
+const BIN_NAME: &str = "dbus-client";
+
+/// Completion functions that ask `dbus-client __complete` first and fall back to the static
+/// completion generated by clap, which knows the subcommands and options.
+///
+/// Bash splits `COMP_WORDS` at the colons of addresses like `unix:path=…` and unique names like
+/// `:1.42`, so the words are split again at whitespace only and the candidates are trimmed to the
+/// part after the last colon bash completes, as `__ltrim_colon_completions` of bash-completion.
+const BASH: &str = r#"
+_dbus-client_dynamic() {
+    local words candidates
+    read -ra words <<< "${COMP_LINE:0:COMP_POINT}"
+    [[ ${COMP_LINE:COMP_POINT-1:1} == [[:space:]] ]] && words+=("")
+
+    local cur=${words[-1]}
+    mapfile -t candidates < <(dbus-client __complete "$((${#words[@]} - 1))" "${words[@]}" 2>/dev/null)
+
+    if [[ ${#candidates[@]} -gt 0 ]]; then
+        COMPREPLY=("${candidates[@]#"${cur%"${cur##*:}"}"}")
+    else
+        _dbus-client "$@"
+    fi
+}
+
+complete -F _dbus-client_dynamic -o bashdefault -o default dbus-client
+"#;
+
+const ZSH: &str = r#"
+_dbus-client_dynamic() {
+    local -a candidates
+    candidates=("${(@f)$(dbus-client __complete $((CURRENT - 1)) "${words[@]}" 2>/dev/null)}")
+
+    if [[ -n "${candidates[1]}" ]]; then
+        compadd -a candidates
+    else
+        _dbus-client "$@"
+    fi
+}
+
+_dbus-client_dynamic "$@"
+"#;
+
+const FISH: &str = r#"
+complete -c dbus-client -a "(dbus-client __complete (count (commandline -opc)) (commandline -opc) (commandline -ct) 2>/dev/null)"
+"#;
+
+/// Global options that take a value, `-d` is handled on its own.
+const GLOBAL_OPTIONS: &[&str] = &["-o", "--output", "-t", "--timeout"];
+
+/// Options of subcommands that take a value.
+const OPTIONS: &[&str] = &[
+    "--max-depth",
+    "--interface",
+    "--arg",
+    "--signature",
+    "--json-args",
+    "--json-args-file",
+    "--fd-output",
+    "--destination",
+    "--type",
+    "--sender",
+    "--member",
+    "--path",
+];
+
+/// What a positional argument of a subcommand names, see [`layout`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Positional {
+    BusName,
+    Path,
+    Interface,
+    Method,
+    Property,
+}
+
+/// The positional arguments of `subcommand` that are completed live, in order.
+fn layout(subcommand: &str) -> &'static [Positional] {
+    use Positional::*;
+
+    match subcommand {
+        "introspect" | "i" | "tree" | "t" => &[BusName, Path],
+        "call" | "c" | "call-raw" => &[BusName, Path, Interface, Method],
+        "get" | "set" => &[BusName, Path, Interface, Property],
+        "get-all" => &[BusName, Path, Interface],
+        _ => &[],
+    }
+}
+
+/// The hidden `__complete INDEX WORD...` subcommand the scripts call, it is not part of [`app`]
+/// because the generated scripts cannot handle its name.
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("__complete")
+        .setting(AppSettings::Hidden)
+        .setting(AppSettings::TrailingVarArg)
+        .setting(AppSettings::AllowLeadingHyphen)
+        .arg(Arg::with_name("index").required(true))
+        .arg(Arg::with_name("words").multiple(true))
+}
+
+/// Prints the completion script for `shell`, one of `bash`, `zsh` or `fish`.
+pub fn print_script(shell: &str) {
+    let mut script = Vec::new();
+
+    app().gen_completions_to(BIN_NAME, shell.parse::<Shell>().unwrap(), &mut script);
+
+    let script = String::from_utf8(script).unwrap();
+    let mut out = io::stdout();
+
+    let _ = match shell {
+        // The zsh script ends with the call of its completion function, ours replaces it.
+        "zsh" => write!(
+            out,
+            "{}{}",
+            script
+                .trim_end()
+                .trim_end_matches(&format!("_{} \"$@\"", BIN_NAME)),
+            ZSH
+        ),
+        "bash" => write!(out, "{}{}", script, BASH),
+        _ => write!(out, "{}{}", script, FISH),
+    };
+}
+
+/// The word of a command line that is completed, the address it uses and the positional
+/// arguments of its subcommand before the word.
+#[derive(Debug, PartialEq)]
+struct Request<'a> {
+    address: Option<&'a str>,
+    positional: Positional,
+    positionals: Vec<&'a str>,
+    word: &'a str,
+}
+
+/// Parses `words`, the whole command line including the program name, completing the word at
+/// `index`. Returns `None` if that word is not a positional argument completed live.
+fn parse<'a>(index: usize, words: &[&'a str]) -> Option<Request<'a>> {
+    let word = words.get(index).copied().unwrap_or_default();
+
+    if word.starts_with('-') {
+        return None;
+    }
+
+    let mut address = None;
+    let mut subcommand = None;
+    let mut positionals = Vec::new();
+    let mut words = words.get(1..index)?.iter().copied();
+
+    while let Some(word) = words.next() {
+        match (subcommand, word) {
+            (None, "-d") => address = Some(words.next()?),
+            (None, word) if word.starts_with("-d") => address = word.get(2..),
+            (None, word) if GLOBAL_OPTIONS.contains(&word) => {
+                words.next()?;
+            }
+            (Some(_), word) if OPTIONS.contains(&word) => {
+                words.next()?;
+            }
+            (_, word) if word.starts_with('-') => {}
+            (None, word) => subcommand = Some(word),
+            (Some(_), word) => positionals.push(word),
+        }
+    }
+
+    Some(Request {
+        address,
+        positional: *layout(subcommand?).get(positionals.len())?,
+        positionals,
+        word,
+    })
+}
+
+/// Candidates for the word at `index` of `words`, queried on the bus the command line uses.
+pub fn complete(index: usize, words: &[&str]) -> Vec<String> {
+    let request = match parse(index, words) {
+        Some(request) => request,
+        None => return Vec::new(),
+    };
+    let address = request
+        .address
+        .map(str::to_string)
+        .or_else(|| env::var("DBUS_CLIENT_ADDRESS").ok())
+        .unwrap_or_else(|| "session".into());
+    let client = match Client::connect(
+        &address,
+        Options {
+            timeout: Duration::from_secs(2),
+            ..Default::default()
+        },
+    ) {
+        Ok(client) => client,
+        Err(_) => return Vec::new(),
+    };
+    let word = request.word;
+
+    let candidates = match (request.positional, request.positionals.as_slice()) {
+        (Positional::BusName, _) => bus_names(&client, word),
+        (Positional::Path, [bus_name]) => {
+            let dir = word.rfind('/').map(|index| &word[..=index]).unwrap_or("/");
+            let parent = match dir.trim_end_matches('/') {
+                "" => "/",
+                parent => parent,
+            };
+            let mut paths = entries(&client, bus_name, parent, |entry| match entry {
+                Entry::Node { name } => vec![format!("{}{}", dir, name)],
+                _ => Vec::new(),
+            });
+
+            paths.push(parent.to_string());
+            paths
+        }
+        (Positional::Interface, [bus_name, path]) => {
+            entries(&client, bus_name, path, |entry| match entry {
+                Entry::Interface { name, .. } => vec![name.clone()],
+                _ => Vec::new(),
+            })
+        }
+        (positional, [bus_name, path, interface_name]) => {
+            entries(&client, bus_name, path, |entry| match entry {
+                Entry::Interface {
+                    name,
+                    methods,
+                    properties,
+                    ..
+                } if name == interface_name => match positional {
+                    Positional::Method => {
+                        methods.iter().map(|method| method.name.clone()).collect()
+                    }
+                    _ => properties
+                        .iter()
+                        .map(|property| property.name.clone())
+                        .collect(),
+                },
+                _ => Vec::new(),
+            })
+        }
+        _ => Vec::new(),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// The bus names on the bus, unique names like `:1.42` only if `word` starts with a colon.
+pub fn bus_names(client: &Client, word: &str) -> Vec<String> {
+    client
+        .list_names()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| word.starts_with(':') || !name.starts_with(':'))
+        .collect()
+}
+
+fn entries(
+    client: &Client,
+    bus_name: &str,
+    path: &str,
+    f: impl FnMut(&Entry) -> Vec<String>,
+) -> Vec<String> {
+    client
+        .introspect(bus_name, path)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(f)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Positional, Request};
+
+    #[test]
+    fn test_parse() {
+        let words = [
+            "dbus-client",
+            "-v",
+            "-d",
+            "system",
+            "call",
+            "--signature",
+            "s",
+            "org.test",
+            "/org/test",
+            "org.te",
+        ];
+
+        assert_eq!(
+            parse(9, &words),
+            Some(Request {
+                address: Some("system"),
+                positional: Positional::Interface,
+                positionals: vec!["org.test", "/org/test"],
+                word: "org.te",
+            })
+        );
+        assert_eq!(
+            parse(2, &["dbus-client", "get", "org"]).map(|request| request.positional),
+            Some(Positional::BusName)
+        );
+        assert_eq!(
+            parse(3, &["dbus-client", "-dsystem", "tree"]).map(|request| request.address),
+            Some(Some("system"))
+        );
+        // Subcommands, options and their values are completed statically.
+        assert_eq!(parse(1, &["dbus-client", "ca"]), None);
+        assert_eq!(parse(3, &["dbus-client", "call", "--signature"]), None);
+        assert_eq!(parse(2, &["dbus-client", "call", "--sig"]), None);
+        assert_eq!(
+            parse(5, &["dbus-client", "get-all", "a.b", "/", "a.b.C"]),
+            None
+        );
+        assert_eq!(parse(2, &["dbus-client", "monitor"]), None);
+        // Addresses and unique names contain colons.
+        assert_eq!(
+            parse(
+                4,
+                &["dbus-client", "-d", "unix:path=/run/x", "call", ":1.4"]
+            ),
+            Some(Request {
+                address: Some("unix:path=/run/x"),
+                positional: Positional::BusName,
+                positionals: vec![],
+                word: ":1.4",
+            })
+        );
+        assert_eq!(
+            parse(
+                4,
+                &["dbus-client", "-dunix:path=/run/x", "get", ":1.42", "/"]
+            )
+            .map(|request| (request.address, request.positionals)),
+            Some((Some("unix:path=/run/x"), vec![":1.42"]))
+        );
+    }
+}
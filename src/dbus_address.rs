@@ -0,0 +1,264 @@
+use std::fmt::{self, Display, Formatter};
+
+use itertools::Itertools;
+
+use crate::dbus_error::DBusError;
+
+/// A single server address as described in the
+/// [D-Bus specification](https://dbus.freedesktop.org/doc/dbus-specification.html#addresses),
+/// e.g. `unix:path=/run/dbus/system_bus_socket` or `tcp:host=localhost,port=12345`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DBusAddress {
+    pub transport: String,
+    pub options: Vec<(String, String)>,
+    raw: String,
+}
+
+impl DBusAddress {
+    /// Parses a `;` separated list of addresses which are meant to be tried in order.
+    pub fn parse_list(str: &str) -> Result<Vec<DBusAddress>, DBusError> {
+        let addresses = str
+            .split(';')
+            .filter(|address| !address.is_empty())
+            .map(DBusAddress::parse)
+            .collect::<Result<Vec<DBusAddress>, DBusError>>()?;
+
+        if addresses.is_empty() {
+            Err(DBusError::InvalidAddress("Empty address".into()))
+        } else {
+            Ok(addresses)
+        }
+    }
+
+    pub fn parse(str: &str) -> Result<DBusAddress, DBusError> {
+        let (transport, options) = str.split_once(':').ok_or_else(|| {
+            DBusError::InvalidAddress(format!("Missing transport in address: {}", str))
+        })?;
+
+        if transport.is_empty() {
+            return Err(DBusError::InvalidAddress(format!(
+                "Missing transport in address: {}",
+                str
+            )));
+        }
+
+        let options = options
+            .split(',')
+            .filter(|option| !option.is_empty())
+            .map(|option| {
+                let (key, value) = option.split_once('=').ok_or_else(|| {
+                    DBusError::InvalidAddress(format!(
+                        "Expected key=value in address: {} got: {}",
+                        str, option
+                    ))
+                })?;
+
+                if key.is_empty() {
+                    return Err(DBusError::InvalidAddress(format!(
+                        "Missing key in address: {}",
+                        str
+                    )));
+                }
+
+                Ok((key.to_string(), unescape(value)?))
+            })
+            .collect::<Result<Vec<(String, String)>, DBusError>>()?;
+
+        if let Some((key, _)) = options.iter().duplicates_by(|(key, _)| key).next() {
+            return Err(DBusError::InvalidAddress(format!(
+                "Duplicate key: {} in address: {}",
+                key, str
+            )));
+        }
+
+        let address = DBusAddress {
+            transport: transport.to_string(),
+            options,
+            raw: str.to_string(),
+        };
+
+        address.validate().map(|_| address)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(option, _)| option == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn validate(&self) -> Result<(), DBusError> {
+        match self.transport.as_str() {
+            "unix" => {
+                let locations = ["path", "abstract", "dir", "tmpdir", "runtime"]
+                    .into_iter()
+                    .filter(|key| self.get(key).is_some())
+                    .collect_vec();
+
+                match locations.len() {
+                    0 => Err(DBusError::InvalidAddress(format!(
+                        "Unix address needs one of path, abstract, dir, tmpdir or runtime: {}",
+                        self
+                    ))),
+                    1 => Ok(()),
+                    _ => Err(DBusError::InvalidAddress(format!(
+                        "Unix address has conflicting keys {}: {}",
+                        locations.join(", "),
+                        self
+                    ))),
+                }
+            }
+            "tcp" | "nonce-tcp" => {
+                match self.get("port").map(|port| port.parse::<u16>()) {
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) => {
+                        return Err(DBusError::InvalidAddress(format!(
+                            "Invalid port in address: {}",
+                            self
+                        )))
+                    }
+                    None => {
+                        return Err(DBusError::InvalidAddress(format!(
+                            "Missing port in address: {}",
+                            self
+                        )))
+                    }
+                }
+
+                match self.get("family") {
+                    None | Some("ipv4") | Some("ipv6") => Ok(()),
+                    Some(family) => Err(DBusError::InvalidAddress(format!(
+                        "Unknown family: {} in address: {}",
+                        family, self
+                    ))),
+                }
+            }
+            "unixexec" => {
+                if self.get("path").is_some() {
+                    Ok(())
+                } else {
+                    Err(DBusError::InvalidAddress(format!(
+                        "Missing path in address: {}",
+                        self
+                    )))
+                }
+            }
+            "launchd" => {
+                if self.get("env").is_some() {
+                    Ok(())
+                } else {
+                    Err(DBusError::InvalidAddress(format!(
+                        "Missing env in address: {}",
+                        self
+                    )))
+                }
+            }
+            "autolaunch" => Ok(()),
+            transport => Err(DBusError::InvalidAddress(format!(
+                "Unknown transport: {} in address: {}",
+                transport, self
+            ))),
+        }
+    }
+}
+
+impl Display for DBusAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// Decodes the `%xx` escapes of an address value and rejects bytes that have to be escaped.
+fn unescape(value: &str) -> Result<String, DBusError> {
+    let mut bytes = Vec::new();
+    let mut chars = value.bytes();
+
+    while let Some(byte) = chars.next() {
+        match byte {
+            b'%' => {
+                let escape = chars.next().zip(chars.next()).and_then(|(high, low)| {
+                    u8::from_str_radix(&String::from_utf8_lossy(&[high, low]), 16).ok()
+                });
+
+                match escape {
+                    Some(byte) => bytes.push(byte),
+                    None => {
+                        return Err(DBusError::InvalidAddress(format!(
+                            "Invalid escape sequence in: {}",
+                            value
+                        )))
+                    }
+                }
+            }
+            b'-' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'_' | b'/' | b'.' | b'*' => {
+                bytes.push(byte)
+            }
+            byte => {
+                return Err(DBusError::InvalidAddress(format!(
+                    "Character '{}' has to be escaped in: {}",
+                    byte as char, value
+                )))
+            }
+        }
+    }
+
+    String::from_utf8(bytes)
+        .map_err(|_| DBusError::InvalidAddress(format!("Invalid UTF-8 in: {}", value)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{dbus_address::DBusAddress, dbus_error::DBusError};
+
+    #[test]
+    fn test_parse() {
+        let addresses =
+            DBusAddress::parse_list("unix:path=/tmp/dbus%2dtest;tcp:host=localhost,port=4000")
+                .unwrap();
+
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(addresses[0].transport, "unix");
+        assert_eq!(addresses[0].get("path"), Some("/tmp/dbus-test"));
+        assert_eq!(addresses[0].to_string(), "unix:path=/tmp/dbus%2dtest");
+        assert_eq!(addresses[1].transport, "tcp");
+        assert_eq!(addresses[1].get("host"), Some("localhost"));
+        assert_eq!(addresses[1].get("port"), Some("4000"));
+
+        assert_eq!(
+            DBusAddress::parse("unix:abstract=/tmp/dbus-XYZ,guid=123")
+                .unwrap()
+                .get("abstract"),
+            Some("/tmp/dbus-XYZ")
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let invalid = [
+            "",
+            "session",
+            ":path=/tmp",
+            "unix:",
+            "unix:path=/tmp,abstract=/tmp",
+            "unix:path=/tmp,path=/var",
+            "unix:path",
+            "unix:path=/tmp%2",
+            "unix:path=/tmp dir",
+            "tcp:host=localhost",
+            "tcp:host=localhost,port=70000",
+            "tcp:host=localhost,port=1,family=ipx",
+            "foo:bar=baz",
+        ];
+
+        invalid.iter().for_each(|address| {
+            assert!(
+                matches!(
+                    DBusAddress::parse_list(address),
+                    Err(DBusError::InvalidAddress(_))
+                ),
+                "{} should be invalid",
+                address
+            )
+        });
+    }
+}
@@ -1,7 +1,19 @@
-use dbus::arg::messageitem::{MessageItem, MessageItemArray, MessageItemDict};
+use std::{
+    fmt::{self, Display, Formatter},
+    os::unix::io::IntoRawFd,
+};
+
+use dbus::{
+    arg::{
+        messageitem::{MessageItem, MessageItemArray, MessageItemDict},
+        OwnedFd,
+    },
+    strings::{Path, Signature},
+};
 use itertools::Itertools;
+use serde_json::{json, Map, Value};
 
-use crate::{dbus_error::DBusError, dbus_type::DBusType, dbus_value::DBusValue};
+use crate::{dbus_error::DBusError, dbus_fd::FdSpec, dbus_type::DBusType, dbus_value::DBusValue};
 
 pub struct DBusArgument<'a> {
     pub dbus_type: &'a DBusType,
@@ -10,184 +22,684 @@ pub struct DBusArgument<'a> {
 
 impl<'a> DBusArgument<'a> {
     pub fn validate(self) -> Result<DBusArgument<'a>, DBusError> {
+        self.dbus_type.is_valid_value(self.dbus_value).map(|_| self)
+    }
+
+    /// Validates a struct of all arguments of a message, the errors name them `arg0`, `arg1` and
+    /// so on.
+    pub fn validate_arguments(self) -> Result<DBusArgument<'a>, DBusError> {
         self.dbus_type
-            .is_valid_value(&self.dbus_value)
+            .is_valid_arguments(self.dbus_value)
             .map(|_| self)
     }
-}
 
-impl<'a> From<DBusArgument<'a>> for Option<MessageItem> {
-    fn from(arg: DBusArgument) -> Self {
-        match arg.dbus_type {
-            DBusType::Boolean => {
-                if let DBusValue::Boolean(value) = arg.dbus_value {
-                    Some(MessageItem::Bool(*value))
-                } else {
-                    panic!("Expected argument of type Boolean got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::Byte => {
-                if let DBusValue::Byte(value) = arg.dbus_value {
-                    Some(MessageItem::Byte(*value))
-                } else {
-                    panic!("Expected argument of type Byte got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::Int16 => {
-                if let DBusValue::Int16(value) = arg.dbus_value {
-                    Some(MessageItem::Int16(*value))
-                } else {
-                    panic!("Expected argument of type Int16 got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::Int32 => {
-                if let DBusValue::Int32(value) = arg.dbus_value {
-                    Some(MessageItem::Int32(*value))
-                } else {
-                    panic!("Expected argument of type Int32 got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::Int64 => {
-                if let DBusValue::Int64(value) = arg.dbus_value {
-                    Some(MessageItem::Int64(*value))
-                } else {
-                    panic!("Expected argument of type Int64 got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::UInt16 => {
-                if let DBusValue::UInt16(value) = arg.dbus_value {
-                    Some(MessageItem::UInt16(*value))
-                } else {
-                    panic!("Expected argument of type UInt16 got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::UInt32 => {
-                if let DBusValue::UInt32(value) = arg.dbus_value {
-                    Some(MessageItem::UInt32(*value))
-                } else {
-                    panic!("Expected argument of type UInt32 got: {:?}", arg.dbus_value)
-                }
-            }
-            DBusType::UInt64 => {
-                if let DBusValue::UInt64(value) = arg.dbus_value {
-                    Some(MessageItem::UInt64(*value))
-                } else {
-                    panic!("Expected argument of type UInt64 got: {:?}", arg.dbus_value)
+    fn fmt_indented(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        match (self.dbus_type, self.dbus_value) {
+            // Basic values carry their type in the literal, the argument type only matters for
+            // containers.
+            (_, DBusValue::Boolean(value)) => write!(f, "{}", value),
+            (_, DBusValue::Byte(value)) => write!(f, "{:02x}y", value),
+            (_, DBusValue::Int16(value)) => write!(f, "{}n", value),
+            (_, DBusValue::Int32(value)) => write!(f, "{}i", value),
+            (_, DBusValue::Int64(value)) => write!(f, "{}x", value),
+            (_, DBusValue::UInt16(value)) => write!(f, "{}q", value),
+            (_, DBusValue::UInt32(value)) => write!(f, "{}u", value),
+            (_, DBusValue::UInt64(value)) => write!(f, "{}t", value),
+            // Infinities are written as `infd` and `-infd` by `Display`.
+            (_, DBusValue::Double(value)) if value.is_nan() => write!(f, "nand"),
+            (_, DBusValue::Double(value)) => write!(f, "{}d", value),
+            (_, DBusValue::String(value)) => write!(f, "\"{}\"", escape(value)),
+            (_, DBusValue::ObjectPath(value)) => write!(f, "o\"{}\"", escape(value)),
+            (_, DBusValue::Signature(value)) => write!(f, "g\"{}\"", escape(value)),
+            (_, DBusValue::UnixFd(FdSpec::Fd(fd))) => write!(f, "{}h", fd),
+            (_, DBusValue::UnixFd(spec)) => write!(f, "\"{}\"", escape(&spec.to_string())),
+            (DBusType::Struct(types), DBusValue::Vec(values)) => fmt_container(
+                f,
+                depth,
+                ("(", ")"),
+                types
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(dbus_type, dbus_value)| {
+                        (
+                            None,
+                            DBusArgument {
+                                dbus_type,
+                                dbus_value,
+                            },
+                        )
+                    })
+                    .collect_vec(),
+            ),
+            (DBusType::Array { value_type }, DBusValue::Vec(values)) => fmt_container(
+                f,
+                depth,
+                ("[", "]"),
+                values
+                    .iter()
+                    .map(|dbus_value| {
+                        (
+                            None,
+                            DBusArgument {
+                                dbus_type: value_type,
+                                dbus_value,
+                            },
+                        )
+                    })
+                    .collect_vec(),
+            ),
+            (
+                DBusType::Dictionary {
+                    key_type,
+                    value_type,
+                },
+                DBusValue::Vec(values),
+            ) => fmt_container(
+                f,
+                depth,
+                ("{", "}"),
+                values
+                    .iter()
+                    .tuples()
+                    .map(|(key, value)| {
+                        (
+                            Some(DBusArgument {
+                                dbus_type: key_type,
+                                dbus_value: key,
+                            }),
+                            DBusArgument {
+                                dbus_type: value_type,
+                                dbus_value: value,
+                            },
+                        )
+                    })
+                    .collect_vec(),
+            ),
+            // Without a container type the elements are written as an untyped struct.
+            (_, DBusValue::Vec(values)) => fmt_container(
+                f,
+                depth,
+                ("(", ")"),
+                values
+                    .iter()
+                    .map(|dbus_value| {
+                        (
+                            None,
+                            DBusArgument {
+                                dbus_type: &DBusType::Unit,
+                                dbus_value,
+                            },
+                        )
+                    })
+                    .collect_vec(),
+            ),
+            (_, DBusValue::Variant(dbus_type, dbus_value)) => {
+                write!(f, "<{} ", String::from(dbus_type.as_ref()))?;
+                DBusArgument {
+                    dbus_type,
+                    dbus_value,
                 }
+                .fmt_indented(f, depth)?;
+                write!(f, ">")
             }
-            DBusType::Double => {
-                if let DBusValue::Double(value) = arg.dbus_value {
-                    Some(MessageItem::Double(*value))
-                } else {
-                    panic!("Expected argument of type Double got: {:?}", arg.dbus_value)
-                }
+            (_, DBusValue::Unit) => Ok(()),
+        }
+    }
+}
+
+/// Renders the argument in the literal syntax accepted by `dbus_value.pest`.
+///
+/// The alternate flag (`{:#}`) spreads containers over multiple indented lines.
+impl<'a> Display for DBusArgument<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Converts the argument into plain JSON.
+///
+/// Numbers, booleans and strings (including object paths and signatures) map to their JSON
+/// counterparts, file descriptors become their number or their spec if they are not opened yet,
+/// arrays and structs become JSON arrays and dictionaries become objects with stringified keys.
+/// Variants are wrapped as `{"type": <signature>, "value": <value>}`.
+impl<'a> From<&DBusArgument<'a>> for Value {
+    fn from(arg: &DBusArgument<'a>) -> Self {
+        match (arg.dbus_type, arg.dbus_value) {
+            (_, DBusValue::Boolean(value)) => json!(value),
+            (_, DBusValue::Byte(value)) => json!(value),
+            (_, DBusValue::Int16(value)) => json!(value),
+            (_, DBusValue::Int32(value)) => json!(value),
+            (_, DBusValue::Int64(value)) => json!(value),
+            (_, DBusValue::UInt16(value)) => json!(value),
+            (_, DBusValue::UInt32(value)) => json!(value),
+            (_, DBusValue::UInt64(value)) => json!(value),
+            (_, DBusValue::Double(value)) => json!(value),
+            (_, DBusValue::String(value)) => json!(value),
+            (_, DBusValue::ObjectPath(value)) => json!(value),
+            (_, DBusValue::Signature(value)) => json!(value),
+            (_, DBusValue::UnixFd(FdSpec::Fd(fd))) => json!(fd),
+            (_, DBusValue::UnixFd(spec)) => json!(spec.to_string()),
+            (DBusType::Struct(types), DBusValue::Vec(values)) => Value::Array(
+                types
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(dbus_type, dbus_value)| {
+                        Value::from(&DBusArgument {
+                            dbus_type,
+                            dbus_value,
+                        })
+                    })
+                    .collect_vec(),
+            ),
+            (DBusType::Array { value_type }, DBusValue::Vec(values)) => Value::Array(
+                values
+                    .iter()
+                    .map(|dbus_value| {
+                        Value::from(&DBusArgument {
+                            dbus_type: value_type,
+                            dbus_value,
+                        })
+                    })
+                    .collect_vec(),
+            ),
+            (
+                DBusType::Dictionary {
+                    key_type,
+                    value_type,
+                },
+                DBusValue::Vec(values),
+            ) => Value::Object(
+                values
+                    .iter()
+                    .tuples()
+                    .map(|(key, value)| {
+                        let key = match Value::from(&DBusArgument {
+                            dbus_type: key_type,
+                            dbus_value: key,
+                        }) {
+                            Value::String(key) => key,
+                            key => key.to_string(),
+                        };
+
+                        (
+                            key,
+                            Value::from(&DBusArgument {
+                                dbus_type: value_type,
+                                dbus_value: value,
+                            }),
+                        )
+                    })
+                    .collect::<Map<String, Value>>(),
+            ),
+            (_, DBusValue::Variant(dbus_type, dbus_value)) => json!({
+                "type": String::from(dbus_type.as_ref()),
+                "value": Value::from(&DBusArgument {
+                    dbus_type,
+                    dbus_value,
+                }),
+            }),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Escapes a string for a double quoted literal.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|char| match char {
+            '\\' => "\\\\".to_string(),
+            '"' => "\\\"".to_string(),
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '\r' => "\\r".to_string(),
+            '\0' => "\\0".to_string(),
+            char if char.is_control() => format!("\\u{{{:x}}}", char as u32),
+            char => char.to_string(),
+        })
+        .collect()
+}
+
+fn fmt_container(
+    f: &mut Formatter<'_>,
+    depth: usize,
+    (open, close): (&str, &str),
+    entries: Vec<(Option<DBusArgument>, DBusArgument)>,
+) -> fmt::Result {
+    write!(f, "{}", open)?;
+
+    for (index, (key, value)) in entries.iter().enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+
+            if !f.alternate() {
+                write!(f, " ")?;
             }
-            DBusType::String => {
-                if let DBusValue::String(value) = arg.dbus_value {
-                    Some(MessageItem::Str(value.clone()))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+        }
+
+        if f.alternate() {
+            write!(f, "\n{}", "    ".repeat(depth + 1))?;
+        }
+
+        if let Some(key) = key {
+            key.fmt_indented(f, depth + 1)?;
+            write!(f, ": ")?;
+        }
+
+        value.fmt_indented(f, depth + 1)?;
+    }
+
+    if f.alternate() && !entries.is_empty() {
+        write!(f, "\n{}", "    ".repeat(depth))?;
+    }
+
+    write!(f, "{}", close)
+}
+
+impl<'a> TryFrom<DBusArgument<'a>> for Option<MessageItem> {
+    type Error = DBusError;
+
+    fn try_from(arg: DBusArgument<'a>) -> Result<Self, DBusError> {
+        arg.to_message_item("value")
+    }
+}
+
+impl<'a> DBusArgument<'a> {
+    /// Converts the argument into a [`MessageItem`], `path` names the argument in errors.
+    pub fn to_message_item(&self, path: &str) -> Result<Option<MessageItem>, DBusError> {
+        let item = match (self.dbus_type, self.dbus_value) {
+            (DBusType::Boolean, DBusValue::Boolean(value)) => MessageItem::Bool(*value),
+            (DBusType::Byte, DBusValue::Byte(value)) => MessageItem::Byte(*value),
+            (DBusType::Int16, DBusValue::Int16(value)) => MessageItem::Int16(*value),
+            (DBusType::Int32, DBusValue::Int32(value)) => MessageItem::Int32(*value),
+            (DBusType::Int64, DBusValue::Int64(value)) => MessageItem::Int64(*value),
+            (DBusType::UInt16, DBusValue::UInt16(value)) => MessageItem::UInt16(*value),
+            (DBusType::UInt32, DBusValue::UInt32(value)) => MessageItem::UInt32(*value),
+            (DBusType::UInt64, DBusValue::UInt64(value)) => MessageItem::UInt64(*value),
+            (DBusType::Double, DBusValue::Double(value)) => MessageItem::Double(*value),
+            (DBusType::String, DBusValue::String(value)) => MessageItem::Str(value.clone()),
+            (DBusType::ObjPath, DBusValue::ObjectPath(value)) => {
+                MessageItem::ObjectPath(Path::new(value.clone()).map_err(|message| {
+                    DBusError::TypeMismatch {
+                        path: path.to_string(),
+                        message,
+                    }
+                })?)
             }
-            DBusType::ObjPath => {
-                if let DBusValue::String(value) = arg.dbus_value {
-                    Some(MessageItem::ObjectPath(value.clone().into()))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+            (DBusType::Signature, DBusValue::Signature(value)) => {
+                MessageItem::Signature(Signature::new(value.clone()).map_err(|message| {
+                    DBusError::TypeMismatch {
+                        path: path.to_string(),
+                        message,
+                    }
+                })?)
             }
-            DBusType::Signature => {
-                if let DBusValue::String(value) = arg.dbus_value {
-                    Some(MessageItem::Signature(value.clone().into()))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+            // The item owns the opened descriptor and closes it when it is dropped, appending it
+            // to a message duplicates it.
+            (DBusType::FileDescriptor, DBusValue::UnixFd(spec)) => {
+                MessageItem::UnixFd(unsafe { OwnedFd::new(spec.open()?.into_raw_fd()) })
             }
-            DBusType::FileDescriptor => {
-                if let DBusValue::String(_) = arg.dbus_value {
-                    todo!()
-                } else {
-                    panic!()
-                }
+            (DBusType::Struct(types), DBusValue::Vec(values)) => MessageItem::Struct(
+                types
+                    .iter()
+                    .zip(values.iter())
+                    .enumerate()
+                    .filter_map(|(index, (dbus_type, dbus_value))| {
+                        DBusArgument {
+                            dbus_type,
+                            dbus_value,
+                        }
+                        .to_message_item(&format!("{}.{}", path, index))
+                        .transpose()
+                    })
+                    .collect::<Result<Vec<MessageItem>, DBusError>>()?,
+            ),
+            (DBusType::Array { value_type }, DBusValue::Vec(values)) => MessageItem::Array(
+                MessageItemArray::new(
+                    values
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(index, dbus_value)| {
+                            DBusArgument {
+                                dbus_type: value_type,
+                                dbus_value,
+                            }
+                            .to_message_item(&format!("{}[{}]", path, index))
+                            .transpose()
+                        })
+                        .collect::<Result<Vec<MessageItem>, DBusError>>()?,
+                    Into::<String>::into(self.dbus_type).into(),
+                )
+                .map_err(|err| DBusError::TypeMismatch {
+                    path: path.to_string(),
+                    message: format!("Invalid array: {:?}", err),
+                })?,
+            ),
+            (
+                DBusType::Dictionary {
+                    key_type,
+                    value_type,
+                },
+                DBusValue::Vec(values),
+            ) => MessageItem::Dict(
+                MessageItemDict::new(
+                    values
+                        .iter()
+                        .tuples()
+                        .enumerate()
+                        .map(|(index, (key, value))| {
+                            let key = DBusArgument {
+                                dbus_type: key_type,
+                                dbus_value: key,
+                            }
+                            .to_message_item(&format!("{}[{}].key", path, index))?;
+                            let value = DBusArgument {
+                                dbus_type: value_type,
+                                dbus_value: value,
+                            }
+                            .to_message_item(&format!("{}[{}].value", path, index))?;
+
+                            Ok(key.zip(value))
+                        })
+                        .filter_map(Result::transpose)
+                        .collect::<Result<Vec<(MessageItem, MessageItem)>, DBusError>>()?,
+                    Into::<String>::into(key_type.as_ref()).into(),
+                    Into::<String>::into(value_type.as_ref()).into(),
+                )
+                .map_err(|err| DBusError::TypeMismatch {
+                    path: path.to_string(),
+                    message: format!("Invalid dictionary: {:?}", err),
+                })?,
+            ),
+            (DBusType::Variant, DBusValue::Variant(dbus_type, dbus_value)) => match (DBusArgument {
+                dbus_type,
+                dbus_value,
+            })
+            .to_message_item(&format!("{}<{}>", path, String::from(dbus_type.as_ref())))?
+            {
+                Some(item) => MessageItem::Variant(Box::new(item)),
+                None => return Ok(None),
+            },
+            (DBusType::Unit, _) => return Ok(None),
+            (dbus_type, dbus_value) => {
+                return Err(DBusError::TypeMismatch {
+                    path: path.to_string(),
+                    message: format!(
+                        "Expected argument of type {} got: {:?}",
+                        String::from(dbus_type),
+                        dbus_value
+                    ),
+                })
             }
-            DBusType::Struct(types) => {
-                if let DBusValue::Vec(values) = arg.dbus_value {
-                    Some(MessageItem::Struct(
-                        types
-                            .iter()
-                            .zip(values.iter())
-                            .map(|pair| {
-                                Into::<Option<MessageItem>>::into(DBusArgument {
-                                    dbus_type: pair.0,
-                                    dbus_value: pair.1,
-                                })
-                                .unwrap()
-                            })
-                            .collect_vec(),
-                    ))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+        };
+
+        Ok(Some(item))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dbus::arg::messageitem::MessageItem;
+    use serde_json::json;
+
+    use crate::{
+        dbus_argument::DBusArgument,
+        dbus_error::DBusError,
+        dbus_fd::{FdSpec, FileMode},
+        dbus_type::DBusType,
+        dbus_value::DBusValue,
+    };
+
+    #[test]
+    fn test_display() {
+        let dbus_type = DBusType::try_from("(sa{sv}ay)").unwrap();
+        let dbus_value = DBusValue::Vec(vec![
+            DBusValue::String("test".into()),
+            DBusValue::Vec(vec![
+                DBusValue::String("count".into()),
+                DBusValue::Variant(Box::new(DBusType::Int32), Box::new(DBusValue::Int32(-8))),
+            ]),
+            DBusValue::Vec(vec![]),
+        ]);
+        let argument = DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        };
+
+        assert_eq!(
+            format!("{}", argument),
+            "(\"test\", {\"count\": <i -8i>}, [])"
+        );
+        assert_eq!(
+            format!("{:#}", argument),
+            "(\n    \"test\",\n    {\n        \"count\": <i -8i>\n    },\n    []\n)"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let dbus_type = DBusType::try_from("(ybnqiuxtda{sas}(sd)oga{ov})").unwrap();
+        let dbus_value = DBusValue::Vec(vec![
+            DBusValue::Byte(255),
+            DBusValue::Boolean(true),
+            DBusValue::Int16(-16),
+            DBusValue::UInt16(16),
+            DBusValue::Int32(-32),
+            DBusValue::UInt32(32),
+            DBusValue::Int64(-64),
+            DBusValue::UInt64(64),
+            DBusValue::Double(-1.5),
+            DBusValue::Vec(vec![
+                DBusValue::String("key".into()),
+                DBusValue::Vec(vec![
+                    DBusValue::String("first value".into()),
+                    DBusValue::String("second value".into()),
+                ]),
+            ]),
+            DBusValue::Vec(vec![
+                DBusValue::String("test".into()),
+                DBusValue::Double(2.0),
+            ]),
+            DBusValue::ObjectPath("/org/test".into()),
+            DBusValue::Signature("a{sv}".into()),
+            DBusValue::Vec(vec![
+                DBusValue::ObjectPath("/".into()),
+                DBusValue::Variant(
+                    Box::new(DBusType::Signature),
+                    Box::new(DBusValue::Signature("ay".into())),
+                ),
+            ]),
+        ]);
+        let argument = DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        };
+
+        assert_eq!(
+            DBusValue::try_from(format!("{}", argument).as_str()).unwrap(),
+            dbus_value
+        );
+        assert_eq!(
+            DBusValue::try_from(format!("{:#}", argument).as_str()).unwrap(),
+            dbus_value
+        );
+    }
+
+    #[test]
+    fn test_display_non_finite_and_untyped() {
+        let dbus_type = DBusType::Double;
+
+        for value in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let dbus_value = DBusValue::Double(value);
+            let literal = DBusArgument {
+                dbus_type: &dbus_type,
+                dbus_value: &dbus_value,
             }
-            DBusType::Array { value_type } => {
-                if let DBusValue::Vec(values) = arg.dbus_value {
-                    Some(MessageItem::Array(
-                        MessageItemArray::new(
-                            values
-                                .iter()
-                                .map(|value| {
-                                    Into::<Option<MessageItem>>::into(DBusArgument {
-                                        dbus_type: value_type,
-                                        dbus_value: value,
-                                    })
-                                    .unwrap()
-                                })
-                                .collect_vec(),
-                            Into::<String>::into(value_type.as_ref()).into(),
-                        )
-                        .unwrap(),
-                    ))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+            .to_string();
+
+            assert!(matches!(
+                DBusValue::try_from(literal.as_str()),
+                Ok(DBusValue::Double(parsed)) if parsed.to_bits() == value.to_bits()
+            ));
+        }
+
+        // Values that do not match their type are still written as literals.
+        let dbus_value = DBusValue::Vec(vec![DBusValue::UInt32(1), DBusValue::Byte(2)]);
+
+        assert_eq!(
+            DBusArgument {
+                dbus_type: &DBusType::String,
+                dbus_value: &dbus_value,
             }
-            DBusType::Dictionary {
-                key_type,
-                value_type,
-            } => {
-                if let DBusValue::Vec(values) = arg.dbus_value {
-                    Some(MessageItem::Dict(
-                        MessageItemDict::new(
-                            values
-                                .iter()
-                                .step_by(2)
-                                .zip(values.iter().skip(1).step_by(2))
-                                .map(|entry| {
-                                    (
-                                        Into::<Option<MessageItem>>::into(DBusArgument {
-                                            dbus_type: key_type,
-                                            dbus_value: entry.0,
-                                        })
-                                        .unwrap(),
-                                        Into::<Option<MessageItem>>::into(DBusArgument {
-                                            dbus_type: value_type,
-                                            dbus_value: entry.1,
-                                        })
-                                        .unwrap(),
-                                    )
-                                })
-                                .collect_vec(),
-                            Into::<String>::into(key_type.as_ref()).into(),
-                            Into::<String>::into(value_type.as_ref()).into(),
-                        )
-                        .unwrap(),
-                    ))
-                } else {
-                    panic!("Expected argument of type String got: {:?}", arg.dbus_value)
-                }
+            .to_string(),
+            "(1u, 02y)"
+        );
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let dbus_type = DBusType::String;
+
+        [
+            "",
+            "plain",
+            "with \"double\" and 'single' quotes",
+            "back\\slash",
+            "line\nbreak\tand tab\r\0",
+            "http://example.com/path?query=a,b:c",
+            "[not] {a: container} <v>",
+            "unicode \u{1f980} and \u{7}",
+        ]
+        .iter()
+        .for_each(|string| {
+            let dbus_value = DBusValue::String(string.to_string());
+            let literal = DBusArgument {
+                dbus_type: &dbus_type,
+                dbus_value: &dbus_value,
             }
-            DBusType::Variant => todo!(),
-            DBusType::Unit => None,
+            .to_string();
+
+            assert_eq!(
+                DBusValue::parse(&literal, &dbus_type),
+                Ok(DBusValue::String(string.to_string())),
+                "{} should round trip",
+                literal
+            );
+        });
+    }
+
+    #[test]
+    fn test_json() {
+        let dbus_type = DBusType::try_from("(oa{uv}(by))").unwrap();
+        let dbus_value = DBusValue::Vec(vec![
+            DBusValue::ObjectPath("/org/test".into()),
+            DBusValue::Vec(vec![
+                DBusValue::UInt32(1),
+                DBusValue::Variant(
+                    Box::new(DBusType::String),
+                    Box::new(DBusValue::String("one".into())),
+                ),
+            ]),
+            DBusValue::Vec(vec![DBusValue::Boolean(false), DBusValue::Byte(7)]),
+        ]);
+
+        assert_eq!(
+            serde_json::Value::from(&DBusArgument {
+                dbus_type: &dbus_type,
+                dbus_value: &dbus_value,
+            }),
+            json!(["/org/test", {"1": {"type": "s", "value": "one"}}, [false, 7]])
+        );
+    }
+
+    #[test]
+    fn test_variant() {
+        let dbus_type = DBusType::try_from("a{sv}").unwrap();
+        let dbus_value =
+            DBusValue::try_from("{\"list\": <as [first, second]>, \"nested\": <v <u 8u>>}")
+                .unwrap();
+        let argument = DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        };
+
+        assert_eq!(
+            format!("{}", argument),
+            "{\"list\": <as [\"first\", \"second\"]>, \"nested\": <v <u 8u>>}"
+        );
+
+        let item = Option::<MessageItem>::try_from(argument.validate().unwrap()).unwrap();
+
+        assert_eq!(
+            item,
+            Some(
+                MessageItem::new_dict(vec![
+                    (
+                        MessageItem::Str("list".into()),
+                        MessageItem::Variant(Box::new(
+                            MessageItem::new_array(vec![
+                                MessageItem::Str("first".into()),
+                                MessageItem::Str("second".into())
+                            ])
+                            .unwrap()
+                        ))
+                    ),
+                    (
+                        MessageItem::Str("nested".into()),
+                        MessageItem::Variant(Box::new(MessageItem::Variant(Box::new(
+                            MessageItem::UInt32(8)
+                        ))))
+                    ),
+                ])
+                .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_variant() {
+        let dbus_type = DBusType::Variant;
+        let dbus_value = DBusValue::try_from("<i 8u>").unwrap();
+
+        assert!(DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
         }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_descriptor() {
+        let dbus_type = DBusType::FileDescriptor;
+        let dbus_value = DBusValue::UnixFd(FdSpec::Fd(9999));
+
+        assert!(matches!(
+            DBusArgument {
+                dbus_type: &dbus_type,
+                dbus_value: &dbus_value,
+            }
+            .to_message_item("arg0"),
+            Err(DBusError::Io(_))
+        ));
+
+        let dbus_value = DBusValue::parse("file:/dev/null", &dbus_type).unwrap();
+        let argument = DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        };
+
+        assert_eq!(argument.to_string(), "\"file:r:/dev/null\"");
+        assert_eq!(
+            DBusValue::parse(&argument.to_string(), &dbus_type),
+            Ok(DBusValue::UnixFd(FdSpec::File {
+                mode: FileMode::Read,
+                path: "/dev/null".into()
+            }))
+        );
+        assert!(matches!(
+            argument.to_message_item("arg0"),
+            Ok(Some(MessageItem::UnixFd(_)))
+        ));
     }
 }
@@ -1,6 +1,187 @@
+use std::fmt::{self, Display, Formatter};
+
+use itertools::Itertools;
+use pest::{
+    error::{Error as PestError, ErrorVariant, InputLocation},
+    RuleType,
+};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum DBusError {
-    InvalidSignature,
-    InvalidValue(String),
-}
\ No newline at end of file
+    /// A type signature could not be parsed, `position` is the byte offset into `signature`.
+    InvalidSignature {
+        signature: String,
+        position: usize,
+        message: String,
+    },
+    /// A value literal could not be parsed, `position` is the byte offset into `value`.
+    InvalidValue {
+        value: String,
+        position: usize,
+        message: String,
+    },
+    /// A value does not match its type, `path` points at the offending part of the value.
+    TypeMismatch {
+        path: String,
+        message: String,
+    },
+    /// Several parts of a value do not match their types, each one is a
+    /// [`DBusError::TypeMismatch`].
+    TypeMismatches(Vec<DBusError>),
+    InvalidName(String),
+    InvalidAddress(String),
+    Connection(String),
+    /// An error returned by the remote side, e.g. `org.freedesktop.DBus.Error.UnknownMethod`.
+    Remote {
+        name: String,
+        message: String,
+    },
+    Timeout(String),
+    NotFound(String),
+    Introspection(String),
+    Unsupported(String),
+    /// Reading or writing a local file failed, e.g. copying a received file descriptor.
+    Io(String),
+    /// The command line does not fit the called method, e.g. the number of arguments is wrong.
+    Usage(String),
+}
+
+impl DBusError {
+    pub fn invalid_signature<R: RuleType>(signature: &str, err: PestError<R>) -> Self {
+        DBusError::InvalidSignature {
+            signature: signature.to_string(),
+            position: position(&err),
+            message: message(&err),
+        }
+    }
+
+    pub fn invalid_value<R: RuleType>(value: &str, err: PestError<R>) -> Self {
+        DBusError::InvalidValue {
+            value: value.to_string(),
+            position: position(&err),
+            message: message(&err),
+        }
+    }
+
+    /// The exit code of the binary for this error, see the README for the full list.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DBusError::InvalidSignature { .. } => 2,
+            DBusError::InvalidValue { .. } => 3,
+            DBusError::TypeMismatch { .. } | DBusError::TypeMismatches(_) => 4,
+            DBusError::InvalidName(_) => 5,
+            DBusError::InvalidAddress(_) => 6,
+            DBusError::Connection(_) => 7,
+            DBusError::Remote { .. } => 8,
+            DBusError::Timeout(_) => 9,
+            DBusError::NotFound(_) => 10,
+            DBusError::Introspection(_) => 11,
+            DBusError::Unsupported(_) => 12,
+            DBusError::Io(_) => 13,
+            // The same code clap exits with on invalid command lines.
+            DBusError::Usage(_) => 1,
+        }
+    }
+}
+
+fn position<R: RuleType>(err: &PestError<R>) -> usize {
+    match err.location {
+        InputLocation::Pos(position) => position,
+        InputLocation::Span((start, _)) => start,
+    }
+}
+
+fn message<R: RuleType>(err: &PestError<R>) -> String {
+    match &err.variant {
+        ErrorVariant::ParsingError { positives, .. } if !positives.is_empty() => format!(
+            "expected {}",
+            positives
+                .iter()
+                .map(|rule| format!("{:?}", rule))
+                .join(", ")
+        ),
+        ErrorVariant::ParsingError { .. } => "unexpected input".to_string(),
+        ErrorVariant::CustomError { message } => message.clone(),
+    }
+}
+
+fn fmt_position(f: &mut Formatter<'_>, input: &str, position: usize) -> fmt::Result {
+    // Positions are byte offsets, which may point into a multibyte character.
+    let mut position = position.min(input.len());
+
+    while !input.is_char_boundary(position) {
+        position -= 1;
+    }
+
+    write!(
+        f,
+        "\n    {}\n    {}^",
+        input,
+        " ".repeat(input[..position].chars().count())
+    )
+}
+
+impl Display for DBusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DBusError::InvalidSignature {
+                signature,
+                position,
+                message,
+            } => {
+                write!(f, "Invalid signature at position {}: {}", position, message)?;
+                fmt_position(f, signature, *position)
+            }
+            DBusError::InvalidValue {
+                value,
+                position,
+                message,
+            } => {
+                write!(f, "Invalid value at position {}: {}", position, message)?;
+                fmt_position(f, value, *position)
+            }
+            DBusError::TypeMismatch { path, message } => {
+                write!(f, "Type mismatch at {}: {}", path, message)
+            }
+            DBusError::TypeMismatches(mismatches) => {
+                write!(f, "{} type mismatches:", mismatches.len())?;
+
+                mismatches
+                    .iter()
+                    .try_for_each(|mismatch| write!(f, "\n    {}", mismatch))
+            }
+            DBusError::InvalidName(message) => write!(f, "Invalid name: {}", message),
+            DBusError::InvalidAddress(message) => write!(f, "Invalid address: {}", message),
+            DBusError::Connection(message) => write!(f, "Connection failed: {}", message),
+            DBusError::Remote { name, message } => write!(f, "{}: {}", name, message),
+            DBusError::Timeout(message) => write!(f, "Timeout: {}", message),
+            DBusError::NotFound(message) => write!(f, "Not found: {}", message),
+            DBusError::Introspection(message) => {
+                write!(f, "Invalid introspection data: {}", message)
+            }
+            DBusError::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            DBusError::Io(message) => write!(f, "I/O error: {}", message),
+            DBusError::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Errors reported by the bus or the remote peer.
+///
+/// Errors that occur while connecting are mapped to [`DBusError::Connection`] explicitly.
+impl From<dbus::Error> for DBusError {
+    fn from(err: dbus::Error) -> Self {
+        let name = err.name().unwrap_or("org.freedesktop.DBus.Error.Failed");
+        let message = err.message().unwrap_or_default().to_string();
+
+        match name {
+            "org.freedesktop.DBus.Error.NoReply" | "org.freedesktop.DBus.Error.Timeout" => {
+                DBusError::Timeout(message)
+            }
+            name => DBusError::Remote {
+                name: name.to_string(),
+                message,
+            },
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    mem::ManuallyDrop,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    },
+};
+
+use crate::{dbus_error::DBusError, dbus_value::DBusValue};
+
+/// How `file:MODE:PATH` opens its file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FileMode {
+    /// `r`, the default.
+    Read,
+    /// `w`, creates or truncates the file.
+    Write,
+    /// `a`, creates the file or appends to it.
+    Append,
+    /// `rw`, creates the file if it does not exist.
+    ReadWrite,
+}
+
+/// A file descriptor argument as given on the command line.
+///
+/// Parsing only records the spec, the descriptor is opened by [`FdSpec::open`] when a message
+/// is built and closed again once the message is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FdSpec {
+    /// A descriptor of this process, `fd:N` or `stdin`. Received descriptors are numbers too,
+    /// they stay owned by their message.
+    Fd(RawFd),
+    /// `file:[MODE:]PATH`. It is not deserialized, data from elsewhere must not open, create or
+    /// truncate files.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    File { mode: FileMode, path: String },
+}
+
+impl FdSpec {
+    /// Parses `fd:N`, `file:[MODE:]PATH` and `stdin` without opening anything.
+    ///
+    /// `MODE` is one of `r` (the default), `w`, `a` or `rw`. Returns `None` if `spec` has none
+    /// of these forms.
+    pub fn parse(spec: &str) -> Option<Result<FdSpec, String>> {
+        if spec == "stdin" {
+            return Some(Ok(FdSpec::Fd(0)));
+        }
+
+        if let Some(fd) = spec.strip_prefix("fd:") {
+            return Some(
+                fd.parse::<RawFd>()
+                    .ok()
+                    .filter(|fd| *fd >= 0)
+                    .map(FdSpec::Fd)
+                    .ok_or_else(|| format!("Invalid file descriptor number: {}", fd)),
+            );
+        }
+
+        let file = spec.strip_prefix("file:")?;
+        let (mode, path) = match file.split_once(':') {
+            Some(("r", path)) => (FileMode::Read, path),
+            Some(("w", path)) => (FileMode::Write, path),
+            Some(("a", path)) => (FileMode::Append, path),
+            Some(("rw", path)) => (FileMode::ReadWrite, path),
+            _ => (FileMode::Read, file),
+        };
+
+        Some(Ok(FdSpec::File {
+            mode,
+            path: path.to_string(),
+        }))
+    }
+
+    /// Opens the descriptor to pass in a message, `Fd` is duplicated so the original stays open.
+    pub fn open(&self) -> Result<OwnedFd, DBusError> {
+        match self {
+            FdSpec::Fd(fd) => unsafe { BorrowedFd::borrow_raw(*fd) }
+                .try_clone_to_owned()
+                .map_err(|err| DBusError::Io(format!("Invalid file descriptor {}: {}", fd, err))),
+            FdSpec::File { mode, path } => {
+                let mut options = OpenOptions::new();
+
+                match mode {
+                    FileMode::Read => options.read(true),
+                    FileMode::Write => options.write(true).create(true).truncate(true),
+                    FileMode::Append => options.append(true).create(true),
+                    FileMode::ReadWrite => options.read(true).write(true).create(true),
+                };
+
+                options
+                    .open(path)
+                    .map(OwnedFd::from)
+                    .map_err(|err| DBusError::Io(format!("Could not open {}: {}", path, err)))
+            }
+        }
+    }
+}
+
+/// Writes the spec as it is parsed, e.g. `fd:3` or `file:w:/tmp/log`.
+impl Display for FdSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FdSpec::Fd(fd) => write!(f, "fd:{}", fd),
+            FdSpec::File { mode, path } => {
+                let mode = match mode {
+                    FileMode::Read => "r",
+                    FileMode::Write => "w",
+                    FileMode::Append => "a",
+                    FileMode::ReadWrite => "rw",
+                };
+
+                write!(f, "file:{}:{}", mode, path)
+            }
+        }
+    }
+}
+
+/// Collects the descriptor numbers contained in `value` in order, e.g. the descriptors of a
+/// reply. Files that are not opened yet have no number.
+pub fn collect(value: &DBusValue) -> Vec<RawFd> {
+    match value {
+        DBusValue::UnixFd(FdSpec::Fd(fd)) => vec![*fd],
+        DBusValue::Vec(values) => values.iter().flat_map(collect).collect(),
+        DBusValue::Variant(_, value) => collect(value),
+        _ => Vec::new(),
+    }
+}
+
+/// Describes a received file descriptor, e.g. `5h: regular file, 120 bytes, mode 644, /tmp/x`.
+pub fn describe(fd: RawFd) -> String {
+    let target = std::fs::read_link(format!("/proc/self/fd/{}", fd))
+        .map(|target| format!(", {}", target.display()))
+        .unwrap_or_default();
+
+    match borrow(fd).metadata() {
+        Ok(metadata) => {
+            let file_type = metadata.file_type();
+            let kind = if file_type.is_file() {
+                "regular file"
+            } else if file_type.is_dir() {
+                "directory"
+            } else if file_type.is_fifo() {
+                "pipe"
+            } else if file_type.is_socket() {
+                "socket"
+            } else if file_type.is_char_device() {
+                "character device"
+            } else if file_type.is_block_device() {
+                "block device"
+            } else {
+                "unknown"
+            };
+
+            format!(
+                "{}h: {}, {} bytes, mode {:o}{}",
+                fd,
+                kind,
+                metadata.len(),
+                metadata.permissions().mode() & 0o7777,
+                target
+            )
+        }
+        Err(err) => format!("{}h: {}{}", fd, err, target),
+    }
+}
+
+/// Copies the remaining contents of `fd` to `out`.
+pub fn copy(fd: RawFd, out: &mut impl Write) -> Result<u64, DBusError> {
+    io::copy(&mut &*borrow(fd), out)
+        .map_err(|err| DBusError::Io(format!("Could not copy fd {}: {}", fd, err)))
+}
+
+/// A `File` for `fd` that does not close it, the descriptor stays owned by its message.
+fn borrow(fd: RawFd) -> ManuallyDrop<File> {
+    ManuallyDrop::new(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env, fs,
+        os::unix::io::{AsRawFd, RawFd},
+    };
+
+    use crate::{
+        dbus_argument::DBusArgument,
+        dbus_fd::{self, FdSpec, FileMode},
+        dbus_type::DBusType,
+        dbus_value::DBusValue,
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(FdSpec::parse("stdin"), Some(Ok(FdSpec::Fd(0))));
+        assert_eq!(FdSpec::parse("fd:3"), Some(Ok(FdSpec::Fd(3))));
+        assert!(matches!(FdSpec::parse("fd:-1"), Some(Err(_))));
+        assert_eq!(FdSpec::parse("/tmp/file"), None);
+        assert_eq!(
+            FdSpec::parse("file:/tmp/a:b"),
+            Some(Ok(FdSpec::File {
+                mode: FileMode::Read,
+                path: "/tmp/a:b".into()
+            }))
+        );
+
+        let spec = FdSpec::parse("file:rw:/tmp/file").unwrap().unwrap();
+
+        assert_eq!(
+            spec,
+            FdSpec::File {
+                mode: FileMode::ReadWrite,
+                path: "/tmp/file".into()
+            }
+        );
+        assert_eq!(FdSpec::parse(&spec.to_string()), Some(Ok(spec)));
+    }
+
+    #[test]
+    fn test_open() {
+        let path = env::temp_dir().join("dbus-client-test-open");
+        fs::write(&path, "contents").unwrap();
+
+        assert!(FdSpec::Fd(9999).open().is_err());
+        assert!(FdSpec::parse("file:/does/not/exist")
+            .unwrap()
+            .unwrap()
+            .open()
+            .is_err());
+
+        let fd = FdSpec::parse(&format!("file:{}", path.display()))
+            .unwrap()
+            .unwrap()
+            .open()
+            .unwrap();
+        let mut contents = Vec::new();
+        dbus_fd::copy(fd.as_raw_fd(), &mut contents).unwrap();
+
+        assert_eq!(contents, b"contents");
+        assert!(dbus_fd::describe(fd.as_raw_fd())
+            .starts_with(&format!("{}h: regular file, 8 bytes", fd.as_raw_fd())));
+
+        let dup = FdSpec::Fd(fd.as_raw_fd()).open().unwrap();
+
+        assert_ne!(dup.as_raw_fd(), fd.as_raw_fd());
+
+        let fd = FdSpec::parse(&format!("file:w:{}", path.display()))
+            .unwrap()
+            .unwrap()
+            .open()
+            .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"");
+        assert!(dbus_fd::copy(fd.as_raw_fd(), &mut Vec::new()).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_does_not_open() {
+        let path = env::temp_dir().join("dbus-client-test-parse");
+        let missing = env::temp_dir().join("dbus-client-test-parse-missing");
+        let dbus_type = DBusType::try_from("(hah)").unwrap();
+        fs::write(&path, "contents").unwrap();
+        let _ = fs::remove_file(&missing);
+
+        let dbus_value = DBusValue::parse(
+            &format!(
+                "('file:w:{}', ['file:a:{}'])",
+                path.display(),
+                missing.display()
+            ),
+            &dbus_type,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"contents");
+        assert!(!missing.exists());
+
+        // The files are opened when the message is built.
+        DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        }
+        .to_message_item("args")
+        .unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"");
+        assert!(missing.exists());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(missing).unwrap();
+    }
+
+    #[test]
+    fn test_parse_and_collect() {
+        let dbus_type = DBusType::try_from("(ahv)").unwrap();
+        let dbus_value = DBusValue::parse("([fd:4, stdin, file:/tmp/x], <h 5h>)", &dbus_type);
+
+        assert_eq!(
+            dbus_value,
+            DBusValue::parse("(['fd:4', 'stdin', 'file:/tmp/x'], <h 5h>)", &dbus_type)
+        );
+
+        let dbus_value = dbus_value.unwrap();
+
+        assert_eq!(dbus_fd::collect(&dbus_value), vec![4, 0, 5] as Vec<RawFd>);
+        assert_eq!(
+            DBusValue::parse("fd:7", &DBusType::FileDescriptor),
+            Ok(DBusValue::UnixFd(FdSpec::Fd(7)))
+        );
+    }
+}
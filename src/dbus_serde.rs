@@ -0,0 +1,763 @@
+use std::fmt::{self, Formatter};
+
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+
+use crate::{
+    dbus_error::DBusError,
+    dbus_fd::FdSpec,
+    dbus_type::DBusType,
+    dbus_value::{validate_object_path, validate_signature, DBusValue},
+};
+
+/// Serializes the type as its signature, e.g. `a{sv}`.
+impl Serialize for DBusType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for DBusType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Deserializes plain data, e.g. JSON, into a value of the given type.
+///
+/// Structs and arrays are sequences, dictionaries are maps whose keys may also be strings of
+/// literals of the key type (JSON object keys are always strings). Variants are maps with
+/// exactly the keys `type`, a single complete signature, and `value`, other values have their type
+/// inferred: booleans `b`, integers `i`, `x` or `t`, numbers `d`, strings `s`, sequences arrays
+/// of a common type or `av` and maps `a{sv}`. File descriptors are numbers or the strings
+/// accepted by [`FdSpec::parse`] except `file:` specs. This is the JSON produced by
+/// [`DBusArgument`](crate::dbus_argument::DBusArgument), so that JSON reads back unchanged.
+///
+/// Descriptor numbers refer to the descriptors of this process, which are duplicated into the
+/// message, so only deserialize data that may pass them on.
+pub struct TypedValue<'a>(pub &'a DBusType);
+
+impl<'de, 'a> DeserializeSeed<'de> for TypedValue<'a> {
+    type Value = DBusValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DBusValue, D::Error> {
+        Seed {
+            dbus_type: self.0,
+            files: false,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+/// Like [`TypedValue`] but also accepts `file:` specs, which open, create or truncate the file
+/// when the message is built. Only for trusted input like the arguments of the command line.
+pub struct TypedValueWithFiles<'a>(pub &'a DBusType);
+
+impl<'de, 'a> DeserializeSeed<'de> for TypedValueWithFiles<'a> {
+    type Value = DBusValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DBusValue, D::Error> {
+        Seed {
+            dbus_type: self.0,
+            files: true,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+/// The seed behind [`TypedValue`] and [`TypedValueWithFiles`], `files` is passed on to nested
+/// values.
+#[derive(Clone, Copy)]
+struct Seed<'a> {
+    dbus_type: &'a DBusType,
+    files: bool,
+}
+
+impl<'a> Seed<'a> {
+    fn nested<'b>(&self, dbus_type: &'b DBusType) -> Seed<'b> {
+        Seed {
+            dbus_type,
+            files: self.files,
+        }
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for Seed<'a> {
+    type Value = DBusValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DBusValue, D::Error> {
+        match self.dbus_type {
+            DBusType::Boolean => bool::deserialize(deserializer).map(DBusValue::Boolean),
+            DBusType::Byte => u8::deserialize(deserializer).map(DBusValue::Byte),
+            DBusType::Int16 => i16::deserialize(deserializer).map(DBusValue::Int16),
+            DBusType::Int32 => i32::deserialize(deserializer).map(DBusValue::Int32),
+            DBusType::Int64 => i64::deserialize(deserializer).map(DBusValue::Int64),
+            DBusType::UInt16 => u16::deserialize(deserializer).map(DBusValue::UInt16),
+            DBusType::UInt32 => u32::deserialize(deserializer).map(DBusValue::UInt32),
+            DBusType::UInt64 => u64::deserialize(deserializer).map(DBusValue::UInt64),
+            DBusType::Double => f64::deserialize(deserializer).map(DBusValue::Double),
+            DBusType::String => String::deserialize(deserializer).map(DBusValue::String),
+            DBusType::ObjPath => {
+                let path = String::deserialize(deserializer)?;
+
+                validate_object_path(&path).map_err(de::Error::custom)?;
+                Ok(DBusValue::ObjectPath(path))
+            }
+            DBusType::Signature => {
+                let signature = String::deserialize(deserializer)?;
+
+                validate_signature(&signature).map_err(de::Error::custom)?;
+                Ok(DBusValue::Signature(signature))
+            }
+            DBusType::FileDescriptor => {
+                deserializer.deserialize_any(FdVisitor { files: self.files })
+            }
+            DBusType::Unit => <()>::deserialize(deserializer).map(|_| DBusValue::Unit),
+            DBusType::Struct(types) => deserializer.deserialize_seq(StructVisitor {
+                types,
+                files: self.files,
+            }),
+            DBusType::Array { value_type } => {
+                deserializer.deserialize_seq(ArrayVisitor(self.nested(value_type)))
+            }
+            DBusType::Dictionary {
+                key_type,
+                value_type,
+            } => deserializer.deserialize_map(DictionaryVisitor {
+                key: self.nested(key_type),
+                value: self.nested(value_type),
+            }),
+            DBusType::Variant => deserializer.deserialize_any(VariantVisitor { files: self.files }),
+        }
+    }
+}
+
+struct FdVisitor {
+    files: bool,
+}
+
+impl<'de> Visitor<'de> for FdVisitor {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a file descriptor number, fd:N, file:PATH or stdin")
+    }
+
+    fn visit_u64<E: de::Error>(self, fd: u64) -> Result<DBusValue, E> {
+        i32::try_from(fd)
+            .map(|fd| DBusValue::UnixFd(FdSpec::Fd(fd)))
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(fd), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, fd: i64) -> Result<DBusValue, E> {
+        u64::try_from(fd)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(fd), &self))
+            .and_then(|fd| self.visit_u64(fd))
+    }
+
+    fn visit_str<E: de::Error>(self, spec: &str) -> Result<DBusValue, E> {
+        FdSpec::parse(spec)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(spec), &self))?
+            .map(DBusValue::UnixFd)
+            .map_err(E::custom)
+            .and_then(|value| check_files(value, self.files))
+    }
+}
+
+/// Rejects `value` if it is a file descriptor of a file spec and `files` is not set.
+fn check_files<E: de::Error>(value: DBusValue, files: bool) -> Result<DBusValue, E> {
+    match value {
+        DBusValue::UnixFd(spec @ FdSpec::File { .. }) if !files => Err(E::custom(format!(
+            "File descriptors of files are not accepted here got: {}",
+            spec
+        ))),
+        value => Ok(value),
+    }
+}
+
+struct StructVisitor<'a> {
+    types: &'a [DBusType],
+    files: bool,
+}
+
+impl<'de, 'a> Visitor<'de> for StructVisitor<'a> {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a sequence of {} values for {}",
+            self.types.len(),
+            signature(self.types)
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DBusValue, A::Error> {
+        let mut values = Vec::new();
+
+        for (index, dbus_type) in self.types.iter().enumerate() {
+            let seed = Seed {
+                dbus_type,
+                files: self.files,
+            };
+
+            values.push(
+                seq.next_element_seed(seed)?
+                    .ok_or_else(|| de::Error::invalid_length(index, &self))?,
+            );
+        }
+
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(self.types.len() + 1, &self));
+        }
+
+        Ok(DBusValue::Vec(values))
+    }
+}
+
+/// The signature of a struct of `types`.
+fn signature(types: &[DBusType]) -> String {
+    format!(
+        "({})",
+        types.iter().map(String::from).collect::<Vec<_>>().join("")
+    )
+}
+
+struct ArrayVisitor<'a>(Seed<'a>);
+
+impl<'de, 'a> Visitor<'de> for ArrayVisitor<'a> {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a sequence of {}", self.0.dbus_type)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DBusValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element_seed(self.0)? {
+            values.push(value);
+        }
+
+        Ok(DBusValue::Vec(values))
+    }
+}
+
+struct DictionaryVisitor<'a> {
+    key: Seed<'a>,
+    value: Seed<'a>,
+}
+
+impl<'de, 'a> Visitor<'de> for DictionaryVisitor<'a> {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a map from {} to {}",
+            self.key.dbus_type, self.value.dbus_type
+        )
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DBusValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(key) = map.next_key_seed(KeySeed(self.key))? {
+            values.push(key);
+            values.push(map.next_value_seed(self.value)?);
+        }
+
+        Ok(DBusValue::Vec(values))
+    }
+}
+
+/// A dictionary key, strings are parsed as literals of non-string key types.
+struct KeySeed<'a>(Seed<'a>);
+
+impl<'de, 'a> DeserializeSeed<'de> for KeySeed<'a> {
+    type Value = DBusValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DBusValue, D::Error> {
+        match self.0.dbus_type {
+            DBusType::String | DBusType::ObjPath | DBusType::Signature => {
+                self.0.deserialize(deserializer)
+            }
+            _ => deserializer.deserialize_any(KeyVisitor(self.0)),
+        }
+    }
+}
+
+struct KeyVisitor<'a>(Seed<'a>);
+
+impl<'a> KeyVisitor<'a> {
+    fn parse<E: de::Error>(&self, key: &str) -> Result<DBusValue, E> {
+        DBusValue::parse(key, self.0.dbus_type)
+            .map_err(E::custom)
+            .and_then(|value| check_files(value, self.0.files))
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for KeyVisitor<'a> {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a key of type {}", self.0.dbus_type)
+    }
+
+    fn visit_bool<E: de::Error>(self, key: bool) -> Result<DBusValue, E> {
+        self.parse(&key.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, key: i64) -> Result<DBusValue, E> {
+        self.parse(&key.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, key: u64) -> Result<DBusValue, E> {
+        self.parse(&key.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, key: f64) -> Result<DBusValue, E> {
+        self.parse(&format!("{:?}", key))
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<DBusValue, E> {
+        self.parse(key)
+    }
+}
+
+struct VariantVisitor {
+    files: bool,
+}
+
+impl VariantVisitor {
+    fn seed<'a>(&self, dbus_type: &'a DBusType) -> Seed<'a> {
+        Seed {
+            dbus_type,
+            files: self.files,
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = DBusValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a value of any type")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<DBusValue, E> {
+        Ok(variant(DBusType::Boolean, DBusValue::Boolean(value)))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<DBusValue, E> {
+        Ok(match i32::try_from(value) {
+            Ok(value) => variant(DBusType::Int32, DBusValue::Int32(value)),
+            Err(_) => variant(DBusType::Int64, DBusValue::Int64(value)),
+        })
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<DBusValue, E> {
+        match i64::try_from(value) {
+            Ok(value) => self.visit_i64(value),
+            Err(_) => Ok(variant(DBusType::UInt64, DBusValue::UInt64(value))),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<DBusValue, E> {
+        Ok(variant(DBusType::Double, DBusValue::Double(value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<DBusValue, E> {
+        Ok(variant(DBusType::String, DBusValue::String(value.into())))
+    }
+
+    /// An array of one inferred type, `av` if the elements differ or there are none.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DBusValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element_seed(self.seed(&DBusType::Variant))? {
+            values.push(value);
+        }
+
+        let uniform = match values.first() {
+            Some(DBusValue::Variant(first, _)) => values.iter().all(
+                |value| matches!(value, DBusValue::Variant(dbus_type, _) if dbus_type == first),
+            ),
+            _ => false,
+        };
+
+        if !uniform {
+            return Ok(variant(
+                DBusType::Array {
+                    value_type: Box::new(DBusType::Variant),
+                },
+                DBusValue::Vec(values),
+            ));
+        }
+
+        let mut value_type = None;
+        let values = values
+            .into_iter()
+            .map(|value| match value {
+                DBusValue::Variant(dbus_type, value) => {
+                    value_type.get_or_insert(*dbus_type);
+                    *value
+                }
+                value => value,
+            })
+            .collect();
+
+        Ok(variant(
+            DBusType::Array {
+                value_type: Box::new(value_type.unwrap()),
+            },
+            DBusValue::Vec(values),
+        ))
+    }
+
+    /// A `{"type": <signature>, "value": <value>}` wrapper in any key order, any other map is an
+    /// `a{sv}`. The entries are buffered because the keys are only known at the end.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DBusValue, A::Error> {
+        let mut entries = Vec::new();
+
+        while let Some(entry) = map.next_entry::<String, Value>()? {
+            entries.push(entry);
+        }
+
+        if let Some((dbus_type, value)) = wrapper(&entries) {
+            return self
+                .seed(&dbus_type)
+                .deserialize(value.clone())
+                .map(|dbus_value| variant(dbus_type, dbus_value))
+                .map_err(de::Error::custom);
+        }
+
+        let mut values = Vec::new();
+
+        for (key, value) in entries {
+            values.push(DBusValue::String(key));
+            values.push(
+                self.seed(&DBusType::Variant)
+                    .deserialize(value)
+                    .map_err(de::Error::custom)?,
+            );
+        }
+
+        Ok(variant(
+            DBusType::Dictionary {
+                key_type: Box::new(DBusType::String),
+                value_type: Box::new(DBusType::Variant),
+            },
+            DBusValue::Vec(values),
+        ))
+    }
+}
+
+/// The type and value of a map with exactly the keys `type` and `value`, where `type` is a
+/// single complete signature.
+fn wrapper(entries: &[(String, Value)]) -> Option<(DBusType, &Value)> {
+    let (signature, value) = match entries {
+        [(first, Value::String(signature)), (second, value)]
+            if first == "type" && second == "value" =>
+        {
+            (signature, value)
+        }
+        [(first, value), (second, Value::String(signature))]
+            if first == "value" && second == "type" =>
+        {
+            (signature, value)
+        }
+        _ => return None,
+    };
+    let mut types = DBusType::parse(signature).ok()?;
+
+    match types.len() {
+        1 => Some((types.remove(0), value)),
+        _ => None,
+    }
+}
+
+fn variant(dbus_type: DBusType, dbus_value: DBusValue) -> DBusValue {
+    DBusValue::Variant(Box::new(dbus_type), Box::new(dbus_value))
+}
+
+/// Converts the JSON text `json` into a value of `dbus_type`, see [`TypedValue`].
+pub fn from_json(json: &str, dbus_type: &DBusType) -> Result<DBusValue, DBusError> {
+    json_value(json, TypedValue(dbus_type))
+}
+
+/// Like [`from_json`] but also accepts `file:` specs, see [`TypedValueWithFiles`].
+pub fn from_json_with_files(json: &str, dbus_type: &DBusType) -> Result<DBusValue, DBusError> {
+    json_value(json, TypedValueWithFiles(dbus_type))
+}
+
+fn json_value<'de>(
+    json: &'de str,
+    seed: impl DeserializeSeed<'de, Value = DBusValue>,
+) -> Result<DBusValue, DBusError> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+
+    seed.deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value))
+        .map_err(|err| DBusError::InvalidValue {
+            value: json.to_string(),
+            position: json
+                .split_inclusive('\n')
+                .take(err.line().saturating_sub(1))
+                .map(str::len)
+                .sum::<usize>()
+                + err.column().saturating_sub(1),
+            message: err.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use serde::de::DeserializeSeed;
+    use serde_json::json;
+
+    use crate::{
+        dbus_argument::DBusArgument,
+        dbus_error::DBusError,
+        dbus_fd::{FdSpec, FileMode},
+        dbus_serde::{self, TypedValue, TypedValueWithFiles},
+        dbus_type::DBusType,
+        dbus_value::DBusValue,
+    };
+
+    #[test]
+    fn test_dbus_type() {
+        let dbus_type = DBusType::try_from("a{sv}").unwrap();
+
+        assert_eq!(serde_json::to_value(&dbus_type).unwrap(), json!("a{sv}"));
+        assert_eq!(
+            serde_json::from_value::<DBusType>(json!("a{sv}")).unwrap(),
+            dbus_type
+        );
+        assert!(serde_json::from_value::<DBusType>(json!("a{vs}")).is_err());
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let dbus_value = DBusValue::Vec(vec![
+            DBusValue::UInt32(8),
+            DBusValue::Int64(-8),
+            DBusValue::ObjectPath("/org/test".into()),
+            DBusValue::UnixFd(FdSpec::Fd(3)),
+            DBusValue::Variant(
+                Box::new(DBusType::try_from("as").unwrap()),
+                Box::new(DBusValue::Vec(vec![DBusValue::String("one".into())])),
+            ),
+            DBusValue::Unit,
+        ]);
+        let value = serde_json::to_value(&dbus_value).unwrap();
+
+        assert_eq!(
+            value,
+            json!({"Vec": [
+                {"UInt32": 8},
+                {"Int64": -8},
+                {"ObjectPath": "/org/test"},
+                {"UnixFd": {"Fd": 3}},
+                {"Variant": ["as", {"Vec": [{"String": "one"}]}]},
+                "Unit"
+            ]})
+        );
+        assert_eq!(
+            serde_json::from_value::<DBusValue>(value).unwrap(),
+            dbus_value
+        );
+        assert!(serde_json::from_value::<DBusValue>(json!({"Byte": 256})).is_err());
+        assert!(
+            serde_json::from_value::<DBusValue>(json!({"Variant": ["a{vs}", "Unit"]})).is_err()
+        );
+        assert!(serde_json::from_value::<DBusValue>(json!({"Float": 1.0})).is_err());
+
+        // File specs are serialized but never deserialized.
+        let file = DBusValue::UnixFd(FdSpec::File {
+            mode: FileMode::Write,
+            path: "/tmp/log".into(),
+        });
+        let value = serde_json::to_value(&file).unwrap();
+
+        assert_eq!(
+            value,
+            json!({"UnixFd": {"File": {"mode": "Write", "path": "/tmp/log"}}})
+        );
+        assert!(serde_json::from_value::<DBusValue>(value).is_err());
+    }
+
+    #[test]
+    fn test_typed_value() {
+        let dbus_type = DBusType::try_from("(oa{uv}(by)h)").unwrap();
+        let dbus_value = TypedValue(&dbus_type)
+            .deserialize(json!([
+                "/org/test",
+                {"1": {"type": "s", "value": "one"}},
+                [false, 7],
+                "fd:3"
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            dbus_value,
+            DBusValue::Vec(vec![
+                DBusValue::ObjectPath("/org/test".into()),
+                DBusValue::Vec(vec![
+                    DBusValue::UInt32(1),
+                    DBusValue::Variant(
+                        Box::new(DBusType::String),
+                        Box::new(DBusValue::String("one".into())),
+                    ),
+                ]),
+                DBusValue::Vec(vec![DBusValue::Boolean(false), DBusValue::Byte(7)]),
+                DBusValue::UnixFd(FdSpec::Fd(3)),
+            ])
+        );
+        // File specs need the opt-in, even nested in variants or as keys, and are not opened
+        // while deserializing.
+        for (dbus_type, json) in [
+            ("h", json!("file:w:/does/not/exist")),
+            (
+                "v",
+                json!({"type": "ah", "value": ["file:w:/does/not/exist"]}),
+            ),
+            ("a{hs}", json!({"file:w:/does/not/exist": "log"})),
+        ] {
+            let dbus_type = DBusType::try_from(dbus_type).unwrap();
+
+            assert!(TypedValue(&dbus_type).deserialize(json.clone()).is_err());
+            assert!(TypedValueWithFiles(&dbus_type).deserialize(json).is_ok());
+        }
+        assert_eq!(
+            dbus_serde::from_json_with_files(
+                r#""file:w:/does/not/exist""#,
+                &DBusType::FileDescriptor
+            ),
+            Ok(DBusValue::UnixFd(FdSpec::File {
+                mode: FileMode::Write,
+                path: "/does/not/exist".into()
+            }))
+        );
+        assert!(
+            dbus_serde::from_json(r#""file:w:/does/not/exist""#, &DBusType::FileDescriptor)
+                .is_err()
+        );
+
+        for json in [
+            json!(["/org/test", {}, [false, 256], 3]),
+            json!(["/org/test", {"x": {"type": "s", "value": "one"}}, [false, 7], 3]),
+            json!(["/org/test", {}, [false, 7, 8], 3]),
+            json!(["/org/test", {}, [false, 7]]),
+            json!(["org", {}, [false, 7], 3]),
+        ] {
+            assert!(TypedValue(&dbus_type).deserialize(json).is_err());
+        }
+
+        // Wrappers are recognized in any key order.
+        assert_eq!(
+            dbus_serde::from_json(r#"{"value": 1, "type": "u"}"#, &DBusType::Variant),
+            Ok(DBusValue::Variant(
+                Box::new(DBusType::UInt32),
+                Box::new(DBusValue::UInt32(1))
+            ))
+        );
+        assert_eq!(
+            dbus_serde::from_json(r#"{"value": 1, "type": "u"}"#, &DBusType::Variant),
+            dbus_serde::from_json(r#"{"type": "u", "value": 1}"#, &DBusType::Variant)
+        );
+        assert!(matches!(
+            dbus_serde::from_json(r#"{"type": "u", "value": -1}"#, &DBusType::Variant),
+            Err(DBusError::InvalidValue { .. })
+        ));
+
+        // Other maps with a type key are dictionaries, e.g. NetworkManager settings.
+        let dbus_type = DBusType::try_from("a{sv}").unwrap();
+
+        for (json, literal) in [
+            (
+                r#"{"conn": {"type": "ethernet", "id": "x"}}"#,
+                "{conn: <a{sv} {type: <s ethernet>, id: <s x>}>}",
+            ),
+            (r#"{"type": "u"}"#, "{type: <s u>}"),
+            (
+                r#"{"type": "su", "value": 1}"#,
+                "{type: <s su>, value: <i 1i>}",
+            ),
+            (
+                r#"{"type": "u", "value": 1, "id": "x"}"#,
+                "{type: <s u>, value: <i 1i>, id: <s x>}",
+            ),
+        ] {
+            assert_eq!(
+                dbus_serde::from_json(json, &dbus_type),
+                DBusValue::parse(literal, &dbus_type),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_typed_value_reads_argument_json() {
+        let dbus_type = DBusType::try_from("(a{sv}a{bd}xg)").unwrap();
+        let dbus_value = DBusValue::parse(
+            "({\"list\": <as [first, second]>, \"nested\": <v <u 8u>>}, {true: 1.5}, -3, 'a{sv}')",
+            &dbus_type,
+        )
+        .unwrap();
+        let json = serde_json::Value::from(&DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        });
+
+        assert_eq!(
+            TypedValue(&dbus_type).deserialize(json).unwrap(),
+            dbus_value
+        );
+    }
+
+    #[test]
+    fn test_inferred_variants() {
+        let dbus_type = DBusType::try_from("a{sv}").unwrap();
+        let json = r#"{
+            "flag": true,
+            "count": 8,
+            "big": 4294967296,
+            "huge": 18446744073709551615,
+            "ratio": 0.5,
+            "names": ["first", "second"],
+            "mixed": [1, "one"],
+            "nested": {"port": {"type": "q", "value": 80}}
+        }"#;
+
+        assert_eq!(
+            dbus_serde::from_json(json, &dbus_type),
+            DBusValue::parse(
+                "{flag: <b true>, count: <i 8i>, big: <x 4294967296x>, \
+                 huge: <t 18446744073709551615t>, ratio: <d 0.5d>, names: <as [first, second]>, \
+                 mixed: <av [<i 1i>, <s one>]>, nested: <a{sv} {port: <q 80q>}>}",
+                &dbus_type
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        assert_eq!(
+            dbus_serde::from_json("[1,\n 2, x]", &DBusType::try_from("ai").unwrap()),
+            Err(DBusError::InvalidValue {
+                value: "[1,\n 2, x]".into(),
+                position: 8,
+                message: "expected value at line 2 column 5".into(),
+            })
+        );
+        assert!(dbus_serde::from_json("[1] [2]", &DBusType::try_from("ai").unwrap()).is_err());
+        assert!(dbus_serde::from_json("null", &DBusType::Variant).is_err());
+    }
+}
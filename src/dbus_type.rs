@@ -1,11 +1,18 @@
-use std::ops::Deref;
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Deref,
+    str::FromStr,
+};
 
 use dbus::Signature as DbusSignature;
 use itertools::Itertools;
 use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 
-use crate::{dbus_error::DBusError, dbus_value::DBusValue};
+use crate::{
+    dbus_error::DBusError,
+    dbus_value::{validate_object_path, validate_signature, DBusValue},
+};
 
 #[derive(Debug, PartialEq)]
 pub enum DBusType {
@@ -36,178 +43,170 @@ pub enum DBusType {
 
 impl DBusType {
     pub fn is_valid_value(&self, val: &DBusValue) -> Result<(), DBusError> {
-        match self {
-            DBusType::Boolean => {
-                if let DBusValue::Boolean(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected boolean got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Byte => {
-                if let DBusValue::Byte(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected byte got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Int16 => {
-                if let DBusValue::Int16(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected int16 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Int32 => {
-                if let DBusValue::Int32(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected int32 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Int64 => {
-                if let DBusValue::Int64(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected int64 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::UInt16 => {
-                if let DBusValue::UInt16(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected uint16 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::UInt32 => {
-                if let DBusValue::UInt32(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected uint32 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::UInt64 => {
-                if let DBusValue::UInt64(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected uint64 got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Double => {
-                if let DBusValue::Double(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected double got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::String => {
-                if let DBusValue::String(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected string got: {:?}",
-                        val
-                    )))
-                }
+        self.is_valid_value_at(val, "value")
+    }
+
+    /// Validates `val` against this type, `path` names `val` in the error.
+    ///
+    /// Containers are validated recursively. A single mismatch is reported as
+    /// [`DBusError::TypeMismatch`], several as [`DBusError::TypeMismatches`].
+    pub fn is_valid_value_at(&self, val: &DBusValue, path: &str) -> Result<(), DBusError> {
+        let mut mismatches = Vec::new();
+
+        self.collect_mismatches(val, path, &mut mismatches);
+
+        mismatches_result(mismatches)
+    }
+
+    /// Validates `val`, the struct of all arguments of a message, against this type. The
+    /// arguments are named `arg0`, `arg1` and so on in the errors.
+    pub fn is_valid_arguments(&self, val: &DBusValue) -> Result<(), DBusError> {
+        let mut mismatches = Vec::new();
+
+        match (self, val) {
+            (DBusType::Struct(types), DBusValue::Vec(values)) if types.len() == values.len() => {
+                types
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .for_each(|(index, (dbus_type, value))| {
+                        dbus_type.collect_mismatches(
+                            value,
+                            &format!("arg{}", index),
+                            &mut mismatches,
+                        )
+                    })
             }
-            DBusType::ObjPath => {
-                if let DBusValue::String(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected string got: {:?}",
-                        val
-                    )))
+            _ => self.collect_mismatches(val, "arguments", &mut mismatches),
+        }
+
+        mismatches_result(mismatches)
+    }
+
+    fn collect_mismatches(&self, val: &DBusValue, path: &str, mismatches: &mut Vec<DBusError>) {
+        let mut mismatch = |message: String| {
+            mismatches.push(DBusError::TypeMismatch {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        match (self, val) {
+            (DBusType::Boolean, DBusValue::Boolean(_))
+            | (DBusType::Byte, DBusValue::Byte(_))
+            | (DBusType::Int16, DBusValue::Int16(_))
+            | (DBusType::Int32, DBusValue::Int32(_))
+            | (DBusType::Int64, DBusValue::Int64(_))
+            | (DBusType::UInt16, DBusValue::UInt16(_))
+            | (DBusType::UInt32, DBusValue::UInt32(_))
+            | (DBusType::UInt64, DBusValue::UInt64(_))
+            | (DBusType::Double, DBusValue::Double(_))
+            | (DBusType::String, DBusValue::String(_))
+            | (DBusType::FileDescriptor, DBusValue::UnixFd(_))
+            | (DBusType::Unit, _) => {}
+            (DBusType::ObjPath, DBusValue::ObjectPath(value)) => {
+                if let Err(message) = validate_object_path(value) {
+                    mismatch(message)
                 }
             }
-            DBusType::Signature => {
-                if let DBusValue::String(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected string got: {:?}",
-                        val
-                    )))
+            (DBusType::Signature, DBusValue::Signature(value)) => {
+                if let Err(message) = validate_signature(value) {
+                    mismatch(message)
                 }
             }
-            DBusType::FileDescriptor => {
-                if let DBusValue::UInt32(_) = val {
-                    Ok(())
+            (DBusType::Struct(types), DBusValue::Vec(values)) => {
+                if types.len() != values.len() {
+                    mismatch(format!(
+                        "Expected {} fields for type {} got: {}",
+                        types.len(),
+                        String::from(self),
+                        values.len()
+                    ))
                 } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected uint32 got: {:?}",
-                        val
-                    )))
+                    types
+                        .iter()
+                        .zip(values)
+                        .enumerate()
+                        .for_each(|(index, (dbus_type, value))| {
+                            dbus_type.collect_mismatches(
+                                value,
+                                &format!("{}.{}", path, index),
+                                mismatches,
+                            )
+                        })
                 }
             }
-            DBusType::Struct(_) => {
-                if let DBusValue::Vec(_) = val {
-                    Ok(())
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected vec got: {:?}",
-                        val
-                    )))
-                }
+            (DBusType::Array { value_type }, DBusValue::Vec(values)) => {
+                values.iter().enumerate().for_each(|(index, value)| {
+                    value_type.collect_mismatches(
+                        value,
+                        &format!("{}[{}]", path, index),
+                        mismatches,
+                    )
+                })
             }
-            DBusType::Array { value_type: _ } => {
-                if let DBusValue::Vec(_vec) = val {
-                    todo!("Check that all values have the same type")
+            (
+                DBusType::Dictionary {
+                    key_type,
+                    value_type,
+                },
+                DBusValue::Vec(values),
+            ) => {
+                if values.len() % 2 != 0 {
+                    mismatch(format!(
+                        "Expected pairs of keys and values got: {} items",
+                        values.len()
+                    ))
                 } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected vec got: {:?}",
-                        val
-                    )))
-                }
-            }
-            DBusType::Dictionary {
-                key_type,
-                value_type,
-            } => {
-                if let DBusValue::Vec(vec) = val {
-                    vec.iter()
-                        .step_by(2)
-                        .map(|inner_val| key_type.is_valid_value(inner_val))
-                        .find(|inner_val| *inner_val != Ok(()))
-                        .map_or(Ok(()), |error| {
-                            Err(DBusError::InvalidValue(format!("Wrong dictionary key")))
+                    values
+                        .iter()
+                        .tuples()
+                        .enumerate()
+                        .for_each(|(index, (key, value))| {
+                            key_type.collect_mismatches(
+                                key,
+                                &format!("{}[{}].key", path, index),
+                                mismatches,
+                            );
+                            value_type.collect_mismatches(
+                                value,
+                                &format!("{}[{}].value", path, index),
+                                mismatches,
+                            );
                         })
-                } else {
-                    Err(DBusError::InvalidValue(format!(
-                        "Expected vec got: {:?}",
-                        val
-                    )))
                 }
             }
-            DBusType::Variant => Ok(()),
-            DBusType::Unit => Ok(()),
+            (DBusType::Variant, DBusValue::Variant(dbus_type, dbus_value)) => dbus_type
+                .collect_mismatches(
+                    dbus_value,
+                    &format!("{}<{}>", path, String::from(dbus_type.as_ref())),
+                    mismatches,
+                ),
+            (dbus_type, val) => mismatch(format!("Expected {} got: {:?}", dbus_type.name(), val)),
+        }
+    }
+
+    /// A human readable name of the type for messages.
+    fn name(&self) -> &'static str {
+        match self {
+            DBusType::Boolean => "boolean",
+            DBusType::Byte => "byte",
+            DBusType::Int16 => "int16",
+            DBusType::Int32 => "int32",
+            DBusType::Int64 => "int64",
+            DBusType::UInt16 => "uint16",
+            DBusType::UInt32 => "uint32",
+            DBusType::UInt64 => "uint64",
+            DBusType::Double => "double",
+            DBusType::String => "string",
+            DBusType::ObjPath => "object path",
+            DBusType::Signature => "signature",
+            DBusType::FileDescriptor => "unix fd",
+            DBusType::Unit => "unit",
+            DBusType::Struct(_) => "struct",
+            DBusType::Array { .. } => "array",
+            DBusType::Dictionary { .. } => "dictionary",
+            DBusType::Variant => "variant",
         }
     }
 }
@@ -222,6 +221,16 @@ impl<'a> From<&DBusType> for DbusSignature<'a> {
     }
 }
 
+/// A single mismatch is reported as [`DBusError::TypeMismatch`], several as
+/// [`DBusError::TypeMismatches`].
+fn mismatches_result(mut mismatches: Vec<DBusError>) -> Result<(), DBusError> {
+    match mismatches.len() {
+        0 => Ok(()),
+        1 => Err(mismatches.remove(0)),
+        _ => Err(DBusError::TypeMismatches(mismatches)),
+    }
+}
+
 impl From<&DBusType> for String {
     fn from(variant: &DBusType) -> Self {
         match variant {
@@ -241,7 +250,7 @@ impl From<&DBusType> for String {
             DBusType::Variant => "v".to_string(),
             DBusType::Struct(value_types) => format!(
                 "({})",
-                value_types.iter().map(|v| Into::<String>::into(v)).join("")
+                value_types.iter().map(Into::<String>::into).join("")
             ),
             DBusType::Array { value_type } => {
                 format!("a{}", Into::<String>::into(value_type.deref()))
@@ -261,62 +270,241 @@ impl From<&DBusType> for String {
     }
 }
 
-impl From<&str> for DBusType {
-    fn from(str: &str) -> DBusType {
-        if str.is_empty() {
-            DBusType::Unit
-        } else {
-            let ast = DBusTypeParser::parse(Rule::dbus_type, str)
-                .expect("Invalid Signature")
-                .next()
-                .unwrap();
-
-            convert_rule(ast.into_inner().next().unwrap())
+/// Signatures are limited to 255 bytes by the specification.
+const MAX_SIGNATURE_LENGTH: usize = 255;
+
+/// Arrays and structs (including dict entries) may each be nested 32 levels deep.
+const MAX_NESTING: usize = 32;
+
+/// Arrays and structs together may be nested 64 levels deep.
+const MAX_TOTAL_NESTING: usize = 64;
+
+impl DBusType {
+    /// Parses a signature of any number of complete types, e.g. `sa{sv}`.
+    ///
+    /// Enforces all rules of the specification: dict entries only as array elements with a
+    /// basic key type, no empty structs, the nesting limits and the maximum length.
+    pub fn parse(signature: &str) -> Result<Vec<DBusType>, DBusError> {
+        if signature.len() > MAX_SIGNATURE_LENGTH {
+            return Err(DBusError::InvalidSignature {
+                signature: signature.to_string(),
+                position: MAX_SIGNATURE_LENGTH,
+                message: format!(
+                    "Signatures must not be longer than {} bytes got: {}",
+                    MAX_SIGNATURE_LENGTH,
+                    signature.len()
+                ),
+            });
+        }
+
+        DBusTypeParser::parse(Rule::signature, signature)
+            .map_err(|err| DBusError::invalid_signature(signature, err))?
+            .next()
+            .unwrap()
+            .into_inner()
+            .filter(|rule| rule.as_rule() != Rule::EOI)
+            .map(|rule| convert_rule(signature, rule, 0, 0))
+            .collect()
+    }
+
+    /// Basic types can be dictionary keys, all others are containers or variants.
+    pub fn is_basic(&self) -> bool {
+        !matches!(
+            self,
+            DBusType::Struct(_)
+                | DBusType::Array { .. }
+                | DBusType::Dictionary { .. }
+                | DBusType::Variant
+                | DBusType::Unit
+        )
+    }
+}
+
+/// Parses a single complete type, the empty signature is [`DBusType::Unit`].
+impl FromStr for DBusType {
+    type Err = DBusError;
+
+    fn from_str(signature: &str) -> Result<DBusType, DBusError> {
+        let mut types = DBusType::parse(signature)?;
+
+        match types.len() {
+            0 => Ok(DBusType::Unit),
+            1 => Ok(types.remove(0)),
+            _ => Err(DBusError::InvalidSignature {
+                signature: signature.to_string(),
+                position: String::from(&types[0]).len(),
+                message: "Expected a single complete type".to_string(),
+            }),
         }
     }
 }
 
-fn convert_rule(rule: Pair<Rule>) -> DBusType {
+impl TryFrom<&str> for DBusType {
+    type Error = DBusError;
+
+    fn try_from(str: &str) -> Result<DBusType, DBusError> {
+        str.parse()
+    }
+}
+
+/// Renders the signature, the inverse of [`DBusType::from_str`].
+impl Display for DBusType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from(self))
+    }
+}
+
+/// Converts a parsed type, `arrays` and `structs` count the enclosing containers.
+fn convert_rule(
+    signature: &str,
+    rule: Pair<Rule>,
+    arrays: usize,
+    structs: usize,
+) -> Result<DBusType, DBusError> {
+    let position = rule.as_span().start();
+    let invalid = |message: String| DBusError::InvalidSignature {
+        signature: signature.to_string(),
+        position,
+        message,
+    };
+
     match rule.as_rule() {
-        Rule::dbus_type => convert_rule(rule.into_inner().next().unwrap()),
-        Rule::array => DBusType::Array {
-            value_type: Box::new(convert_rule(rule.into_inner().next().unwrap())),
-        },
-        Rule::struct_t => DBusType::Struct(
-            rule.into_inner()
-                .map(|inner_rule| convert_rule(inner_rule))
-                .collect_vec(),
+        Rule::signature | Rule::dbus_type => convert_rule(
+            signature,
+            rule.into_inner().next().unwrap(),
+            arrays,
+            structs,
         ),
-        Rule::dictionary => {
-            let mut inner_rule = rule.into_inner();
+        Rule::array => {
+            check_nesting(signature, position, arrays + 1, structs)?;
 
-            DBusType::Dictionary {
-                key_type: Box::new(convert_rule(inner_rule.next().unwrap())),
-                value_type: Box::new(convert_rule(inner_rule.next().unwrap())),
+            let value_type = rule.into_inner().next().unwrap();
+            let value_rule = value_type.clone().into_inner().next().unwrap();
+
+            if value_rule.as_rule() == Rule::dict_entry {
+                convert_dict_entry(signature, value_rule, arrays + 1, structs)
+            } else {
+                Ok(DBusType::Array {
+                    value_type: Box::new(convert_rule(signature, value_type, arrays + 1, structs)?),
+                })
             }
         }
-        Rule::BOOLEAN => DBusType::Boolean,
-        Rule::BYTE => DBusType::Byte,
-        Rule::INT_16 => DBusType::Int16,
-        Rule::INT_32 => DBusType::Int32,
-        Rule::INT_64 => DBusType::Int64,
-        Rule::U_INT_16 => DBusType::UInt16,
-        Rule::U_INT_32 => DBusType::UInt32,
-        Rule::U_INT_64 => DBusType::UInt64,
-        Rule::DOUBLE => DBusType::Double,
-        Rule::STRING => DBusType::String,
-        Rule::OBJ_PATH => DBusType::ObjPath,
-        Rule::SIGNATURE => DBusType::Signature,
-        Rule::FILE_DESCRIPTOR => DBusType::FileDescriptor,
-        Rule::VARIANT => DBusType::Variant,
+        Rule::struct_t => {
+            check_nesting(signature, position, arrays, structs + 1)?;
+
+            let fields = rule.into_inner().collect_vec();
+
+            if fields.is_empty() {
+                return Err(invalid("Structs must have at least one field".to_string()));
+            }
+
+            Ok(DBusType::Struct(
+                fields
+                    .into_iter()
+                    .map(|field| convert_rule(signature, field, arrays, structs + 1))
+                    .collect::<Result<Vec<DBusType>, DBusError>>()?,
+            ))
+        }
+        Rule::dict_entry => Err(invalid(
+            "Dict entries are only allowed as array elements".to_string(),
+        )),
+        Rule::BOOLEAN => Ok(DBusType::Boolean),
+        Rule::BYTE => Ok(DBusType::Byte),
+        Rule::INT_16 => Ok(DBusType::Int16),
+        Rule::INT_32 => Ok(DBusType::Int32),
+        Rule::INT_64 => Ok(DBusType::Int64),
+        Rule::U_INT_16 => Ok(DBusType::UInt16),
+        Rule::U_INT_32 => Ok(DBusType::UInt32),
+        Rule::U_INT_64 => Ok(DBusType::UInt64),
+        Rule::DOUBLE => Ok(DBusType::Double),
+        Rule::STRING => Ok(DBusType::String),
+        Rule::OBJ_PATH => Ok(DBusType::ObjPath),
+        Rule::SIGNATURE => Ok(DBusType::Signature),
+        Rule::FILE_DESCRIPTOR => Ok(DBusType::FileDescriptor),
+        Rule::VARIANT => Ok(DBusType::Variant),
+        Rule::EOI => unreachable!("End of input is never converted"),
     }
 }
 
+/// Converts the dict entry of an array into a dictionary.
+fn convert_dict_entry(
+    signature: &str,
+    rule: Pair<Rule>,
+    arrays: usize,
+    structs: usize,
+) -> Result<DBusType, DBusError> {
+    let position = rule.as_span().start();
+
+    check_nesting(signature, position, arrays, structs + 1)?;
+
+    let types = rule.into_inner().collect_vec();
+
+    if types.len() != 2 {
+        return Err(DBusError::InvalidSignature {
+            signature: signature.to_string(),
+            position,
+            message: format!(
+                "Dict entries must have a key and a value type got: {} types",
+                types.len()
+            ),
+        });
+    }
+
+    let key_position = types[0].as_span().start();
+    let mut types = types
+        .into_iter()
+        .map(|dbus_type| convert_rule(signature, dbus_type, arrays, structs + 1));
+    let key_type = types.next().unwrap()?;
+
+    if !key_type.is_basic() {
+        return Err(DBusError::InvalidSignature {
+            signature: signature.to_string(),
+            position: key_position,
+            message: format!("Dict entry keys must be basic types got: {}", key_type),
+        });
+    }
+
+    Ok(DBusType::Dictionary {
+        key_type: Box::new(key_type),
+        value_type: Box::new(types.next().unwrap()?),
+    })
+}
+
+fn check_nesting(
+    signature: &str,
+    position: usize,
+    arrays: usize,
+    structs: usize,
+) -> Result<(), DBusError> {
+    let message = if arrays > MAX_NESTING {
+        format!(
+            "Arrays must not be nested deeper than {} levels",
+            MAX_NESTING
+        )
+    } else if structs > MAX_NESTING {
+        format!(
+            "Structs must not be nested deeper than {} levels",
+            MAX_NESTING
+        )
+    } else if arrays + structs > MAX_TOTAL_NESTING {
+        format!(
+            "Containers must not be nested deeper than {} levels",
+            MAX_TOTAL_NESTING
+        )
+    } else {
+        return Ok(());
+    };
+
+    Err(DBusError::InvalidSignature {
+        signature: signature.to_string(),
+        position,
+        message,
+    })
+}
+
 #[cfg(test)]
 mod test {
-    use dbus::Signature as DbusSignature;
-
-    use crate::dbus_type::DBusType;
+    use crate::{dbus_error::DBusError, dbus_type::DBusType, dbus_value::DBusValue};
 
     #[test]
     fn test_conversions() {
@@ -346,8 +534,286 @@ mod test {
     #[test]
     fn test_invert() {
         assert_eq!(
-            Into::<DBusType>::into("(si)"),
-            DBusType::Struct(vec![DBusType::String, DBusType::Int32])
+            DBusType::try_from("(si)"),
+            Ok(DBusType::Struct(vec![DBusType::String, DBusType::Int32]))
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            DBusType::try_from("(si"),
+            Err(DBusError::InvalidSignature { position: 3, .. })
+        ));
+        assert!(matches!(
+            DBusType::try_from("sz"),
+            Err(DBusError::InvalidSignature { position: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_type_mismatch_path() {
+        let dbus_type = DBusType::try_from("a{is}").unwrap();
+
+        assert_eq!(
+            dbus_type.is_valid_value_at(
+                &DBusValue::Vec(vec![
+                    DBusValue::Int32(1),
+                    DBusValue::String("one".into()),
+                    DBusValue::String("two".into()),
+                    DBusValue::String("two".into()),
+                ]),
+                "arg0"
+            ),
+            Err(DBusError::TypeMismatch {
+                path: "arg0[1].key".into(),
+                message: "Expected int32 got: String(\"two\")".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_arguments_mismatch_path() {
+        let dbus_type = DBusType::try_from("(sa{sv})").unwrap();
+
+        assert_eq!(
+            dbus_type.is_valid_arguments(&DBusValue::Vec(vec![
+                DBusValue::Int32(1),
+                DBusValue::Vec(vec![DBusValue::Int32(2), DBusValue::Int32(3)]),
+            ])),
+            Err(DBusError::TypeMismatches(vec![
+                DBusError::TypeMismatch {
+                    path: "arg0".into(),
+                    message: "Expected string got: Int32(1)".into()
+                },
+                DBusError::TypeMismatch {
+                    path: "arg1[0].key".into(),
+                    message: "Expected string got: Int32(2)".into()
+                },
+                DBusError::TypeMismatch {
+                    path: "arg1[0].value".into(),
+                    message: "Expected variant got: Int32(3)".into()
+                }
+            ]))
+        );
+        assert!(matches!(
+            dbus_type.is_valid_arguments(&DBusValue::Vec(vec![DBusValue::Int32(1)])),
+            Err(DBusError::TypeMismatch { path, .. }) if path == "arguments"
+        ));
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let dbus_type = DBusType::try_from("ai").unwrap();
+
+        assert_eq!(
+            dbus_type.is_valid_value(&DBusValue::Vec(vec![
+                DBusValue::Int32(1),
+                DBusValue::Int32(2)
+            ])),
+            Ok(())
+        );
+        assert_eq!(
+            dbus_type.is_valid_value_at(
+                &DBusValue::Vec(vec![
+                    DBusValue::Int32(1),
+                    DBusValue::UInt32(2),
+                    DBusValue::String("three".into()),
+                ]),
+                "arg0"
+            ),
+            Err(DBusError::TypeMismatches(vec![
+                DBusError::TypeMismatch {
+                    path: "arg0[1]".into(),
+                    message: "Expected int32 got: UInt32(2)".into()
+                },
+                DBusError::TypeMismatch {
+                    path: "arg0[2]".into(),
+                    message: "Expected int32 got: String(\"three\")".into()
+                }
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_dictionary_values() {
+        let dbus_type = DBusType::try_from("a{sas}").unwrap();
+
+        assert_eq!(
+            dbus_type.is_valid_value_at(
+                &DBusValue::Vec(vec![
+                    DBusValue::String("one".into()),
+                    DBusValue::Vec(vec![DBusValue::String("1".into())]),
+                    DBusValue::String("two".into()),
+                    DBusValue::Vec(vec![DBusValue::Int32(2)]),
+                ]),
+                "arg0"
+            ),
+            Err(DBusError::TypeMismatch {
+                path: "arg0[1].value[0]".into(),
+                message: "Expected string got: Int32(2)".into()
+            })
+        );
+        assert!(matches!(
+            dbus_type.is_valid_value(&DBusValue::Vec(vec![DBusValue::String("one".into())])),
+            Err(DBusError::TypeMismatch { path, .. }) if path == "value"
+        ));
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let dbus_type = DBusType::try_from("(sov)").unwrap();
+
+        assert_eq!(
+            dbus_type.is_valid_value(&DBusValue::Vec(vec![
+                DBusValue::String("name".into()),
+                DBusValue::ObjectPath("/org/test".into()),
+                DBusValue::Variant(Box::new(DBusType::Byte), Box::new(DBusValue::Byte(1))),
+            ])),
+            Ok(())
+        );
+        assert_eq!(
+            dbus_type.is_valid_value_at(
+                &DBusValue::Vec(vec![
+                    DBusValue::String("name".into()),
+                    DBusValue::ObjectPath("/org/".into()),
+                    DBusValue::Variant(Box::new(DBusType::Byte), Box::new(DBusValue::Int32(1))),
+                ]),
+                "arg0"
+            ),
+            Err(DBusError::TypeMismatches(vec![
+                DBusError::TypeMismatch {
+                    path: "arg0.1".into(),
+                    message: "Object path contains an empty element: /org/".into()
+                },
+                DBusError::TypeMismatch {
+                    path: "arg0.2<y>".into(),
+                    message: "Expected byte got: Int32(1)".into()
+                }
+            ]))
+        );
+        assert_eq!(
+            dbus_type.is_valid_value_at(
+                &DBusValue::Vec(vec![DBusValue::String("name".into())]),
+                "arg0"
+            ),
+            Err(DBusError::TypeMismatch {
+                path: "arg0".into(),
+                message: "Expected 3 fields for type (sov) got: 1".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_signature() {
+        assert_eq!(DBusType::parse(""), Ok(vec![]));
+        assert_eq!(
+            DBusType::parse("sa{sv}h"),
+            Ok(vec![
+                DBusType::String,
+                DBusType::Dictionary {
+                    key_type: Box::new(DBusType::String),
+                    value_type: Box::new(DBusType::Variant)
+                },
+                DBusType::FileDescriptor
+            ])
+        );
+
+        let invalid = |signature: &str| match DBusType::parse(signature) {
+            Err(DBusError::InvalidSignature {
+                position, message, ..
+            }) => (position, message),
+            result => panic!("{} should be invalid got: {:?}", signature, result),
+        };
+
+        assert_eq!(
+            invalid("a{(i)s}"),
+            (
+                2,
+                "Dict entry keys must be basic types got: (i)".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("sa{vs}"),
+            (3, "Dict entry keys must be basic types got: v".to_string())
+        );
+        assert_eq!(
+            invalid("i{ss}"),
+            (
+                1,
+                "Dict entries are only allowed as array elements".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("a{sss}"),
+            (
+                1,
+                "Dict entries must have a key and a value type got: 3 types".to_string()
+            )
         );
+        assert_eq!(
+            invalid("(i())"),
+            (2, "Structs must have at least one field".to_string())
+        );
+        assert_eq!(invalid(&"i".repeat(256)).0, 255);
+
+        // The length limit falls into the two bytes of the é.
+        let signature = format!("{}é", "i".repeat(254));
+
+        assert!(DBusType::parse(&signature)
+            .unwrap_err()
+            .to_string()
+            .ends_with(&format!("\n    {}\n    {}^", signature, " ".repeat(254))));
+        assert_eq!(invalid("a").0, 1);
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}i{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert!(DBusType::parse(&nested("a", "", 32)).is_ok());
+        assert!(DBusType::parse(&nested("(", ")", 32)).is_ok());
+        assert!(DBusType::parse(&format!("{}{}", "a".repeat(32), nested("(", ")", 32))).is_ok());
+        assert!(DBusType::parse(&nested("a{s", "}", 32)).is_ok());
+
+        assert!(matches!(
+            DBusType::parse(&nested("a", "", 33)),
+            Err(DBusError::InvalidSignature { position: 32, message, .. })
+                if message == "Arrays must not be nested deeper than 32 levels"
+        ));
+        assert!(matches!(
+            DBusType::parse(&nested("(", ")", 33)),
+            Err(DBusError::InvalidSignature { position: 32, .. })
+        ));
+        assert!(matches!(
+            DBusType::parse(&format!(
+                "{}{}{}",
+                "(".repeat(16),
+                nested("a{s", "}", 17),
+                ")".repeat(16)
+            )),
+            Err(DBusError::InvalidSignature { position: 65, message, .. })
+                if message == "Structs must not be nested deeper than 32 levels"
+        ));
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        ["y", "as", "a{oa{sv}}", "(ybnqiuxtdsogh)", "aa(sa{yv})", "v"]
+            .iter()
+            .for_each(|signature| {
+                let dbus_type = signature.parse::<DBusType>().unwrap();
+
+                assert_eq!(dbus_type.to_string(), *signature);
+                assert_eq!(dbus_type.to_string().parse(), Ok(dbus_type));
+            });
+
+        assert_eq!("".parse(), Ok(DBusType::Unit));
+        assert!(matches!(
+            "sa{sv}".parse::<DBusType>(),
+            Err(DBusError::InvalidSignature { position: 1, .. })
+        ));
     }
 }
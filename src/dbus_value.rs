@@ -1,10 +1,16 @@
+use std::{any::type_name, os::unix::prelude::AsRawFd, str::FromStr};
+
+use dbus::arg::messageitem::MessageItem;
 use itertools::Itertools;
 use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 
-use crate::dbus_type::DBusType;
+use crate::{dbus_error::DBusError, dbus_fd::FdSpec, dbus_type::DBusType};
 
+/// With the `serde` feature values are serialized tagged with their variant, e.g.
+/// `{"UInt32": 8}` in JSON, so integer widths survive a round trip.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DBusValue {
     Boolean(bool),
     Byte(u8),
@@ -16,7 +22,12 @@ pub enum DBusValue {
     UInt64(u64),
     Double(f64),
     String(String),
+    ObjectPath(String),
+    Signature(String),
+    /// A file descriptor, files are only opened when the value is sent.
+    UnixFd(FdSpec),
     Vec(Vec<DBusValue>),
+    Variant(Box<DBusType>, Box<DBusValue>),
     Unit,
 }
 
@@ -24,183 +35,714 @@ pub enum DBusValue {
 #[grammar = "dbus_value.pest"]
 struct ValueParser;
 
-impl From<&str> for DBusValue {
-    fn from(str: &str) -> Self {
+impl TryFrom<&str> for DBusValue {
+    type Error = DBusError;
+
+    fn try_from(str: &str) -> Result<Self, DBusError> {
         if str.is_empty() {
-            DBusValue::Unit
+            Ok(DBusValue::Unit)
         } else {
-            let rule = ValueParser::parse(Rule::dbus_value, str)
-                .expect("Invalid Value")
+            let rule = ValueParser::parse(Rule::value, str)
+                .map_err(|err| DBusError::invalid_value(str, err))?
                 .next()
                 .unwrap();
 
-            convert_rule(rule)
+            convert_rule(str, rule)
         }
     }
 }
 
+/// Converts a received item, fails if a variant has a signature [`DBusType`] does not support.
+impl TryFrom<&MessageItem> for DBusValue {
+    type Error = DBusError;
+
+    fn try_from(item: &MessageItem) -> Result<Self, DBusError> {
+        Ok(match item {
+            MessageItem::Array(values) => DBusValue::Vec(
+                values
+                    .iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            MessageItem::Struct(values) => DBusValue::Vec(
+                values
+                    .iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            MessageItem::Variant(value) => DBusValue::Variant(
+                Box::new(DBusType::try_from(&*value.signature())?),
+                Box::new(value.as_ref().try_into()?),
+            ),
+            MessageItem::Dict(entries) => DBusValue::Vec(
+                entries
+                    .iter()
+                    .map(|(key, value)| Ok([key.try_into()?, value.try_into()?]))
+                    .collect::<Result<Vec<[DBusValue; 2]>, DBusError>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect_vec(),
+            ),
+            MessageItem::ObjectPath(value) => DBusValue::ObjectPath(value.to_string()),
+            MessageItem::Signature(value) => DBusValue::Signature(value.to_string()),
+            MessageItem::Str(value) => DBusValue::String(value.clone()),
+            MessageItem::Bool(value) => DBusValue::Boolean(*value),
+            MessageItem::Byte(value) => DBusValue::Byte(*value),
+            MessageItem::Int16(value) => DBusValue::Int16(*value),
+            MessageItem::Int32(value) => DBusValue::Int32(*value),
+            MessageItem::Int64(value) => DBusValue::Int64(*value),
+            MessageItem::UInt16(value) => DBusValue::UInt16(*value),
+            MessageItem::UInt32(value) => DBusValue::UInt32(*value),
+            MessageItem::UInt64(value) => DBusValue::UInt64(*value),
+            MessageItem::Double(value) => DBusValue::Double(*value),
+            MessageItem::UnixFd(value) => DBusValue::UnixFd(FdSpec::Fd(value.as_raw_fd())),
+        })
+    }
+}
+
 impl DBusValue {
-    pub fn is_type(&self, typ: DBusType) -> bool {
-        match self {
-            DBusValue::Boolean(_) => {
-                if let DBusType::Boolean = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Byte(_) => {
-                if let DBusType::Byte = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Int16(_) => {
-                if let DBusType::Int16 = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Int32(_) => {
-                if let DBusType::Int32 = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Int64(_) => {
-                if let DBusType::Int64 = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::UInt16(_) => {
-                if let DBusType::UInt16 = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::UInt32(_) => {
-                if let DBusType::UInt32 = typ {
-                    true
-                } else {
-                    false
-                }
+    /// Parses `input` as a value of `dbus_type`.
+    ///
+    /// Unlike [`DBusValue::try_from`] literals don't need type suffixes, e.g. `8` is parsed as
+    /// `UInt64` for `t` and as `Double` for `d`. Suffixed literals are accepted if they match
+    /// the type. An empty input is an empty string for `s`. File descriptors for `h` are given as
+    /// `fd:N`, `file:[MODE:]PATH` or `stdin`, see [`FdSpec::parse`].
+    pub fn parse(input: &str, dbus_type: &DBusType) -> Result<DBusValue, DBusError> {
+        if let DBusType::FileDescriptor = dbus_type {
+            if let Some(spec) = FdSpec::parse(input) {
+                return spec
+                    .map(DBusValue::UnixFd)
+                    .map_err(|message| DBusError::InvalidValue {
+                        value: input.to_string(),
+                        position: 0,
+                        message,
+                    });
             }
-            DBusValue::UInt64(_) => {
-                if let DBusType::UInt64 = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Double(_) => {
-                if let DBusType::Double = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::String(_) => {
-                if let DBusType::String = typ {
-                    true
-                } else {
-                    false
-                }
-            }
-            DBusValue::Vec(_) => match typ {
-                DBusType::Struct(_) => true,
-                DBusType::Array { value_type: _ } => true,
-                DBusType::Dictionary {
-                    key_type: _,
-                    value_type: _,
-                } => true,
-                _ => false,
-            },
-            DBusValue::Unit => {
-                if let DBusType::Unit = typ {
-                    true
-                } else {
-                    false
-                }
+        }
+
+        match (dbus_type, input) {
+            (DBusType::Unit, "") => Ok(DBusValue::Unit),
+            (DBusType::String, "") => Ok(DBusValue::String(String::new())),
+            _ => {
+                let rule = ValueParser::parse(Rule::value, input)
+                    .map_err(|err| DBusError::invalid_value(input, err))?
+                    .next()
+                    .unwrap();
+
+                convert_typed_rule(input, rule, dbus_type)
             }
         }
     }
+
+    pub fn is_type(&self, typ: DBusType) -> bool {
+        match self {
+            DBusValue::Boolean(_) => matches!(typ, DBusType::Boolean),
+            DBusValue::Byte(_) => matches!(typ, DBusType::Byte),
+            DBusValue::Int16(_) => matches!(typ, DBusType::Int16),
+            DBusValue::Int32(_) => matches!(typ, DBusType::Int32),
+            DBusValue::Int64(_) => matches!(typ, DBusType::Int64),
+            DBusValue::UInt16(_) => matches!(typ, DBusType::UInt16),
+            DBusValue::UInt32(_) => matches!(typ, DBusType::UInt32),
+            DBusValue::UInt64(_) => matches!(typ, DBusType::UInt64),
+            DBusValue::Double(_) => matches!(typ, DBusType::Double),
+            DBusValue::String(_) => matches!(typ, DBusType::String),
+            DBusValue::ObjectPath(_) => matches!(typ, DBusType::ObjPath),
+            DBusValue::Signature(_) => matches!(typ, DBusType::Signature),
+            DBusValue::UnixFd(_) => matches!(typ, DBusType::FileDescriptor),
+            DBusValue::Vec(_) => matches!(
+                typ,
+                DBusType::Struct(_)
+                    | DBusType::Array { value_type: _ }
+                    | DBusType::Dictionary {
+                        key_type: _,
+                        value_type: _,
+                    }
+            ),
+            DBusValue::Variant(_, _) => matches!(typ, DBusType::Variant),
+            DBusValue::Unit => matches!(typ, DBusType::Unit),
+        }
+    }
 }
 
-fn convert_rule(rule: Pair<Rule>) -> DBusValue {
+fn convert_rule(input: &str, rule: Pair<Rule>) -> Result<DBusValue, DBusError> {
     match rule.as_rule() {
-        Rule::dbus_value => convert_rule(rule.into_inner().next().unwrap()),
-        Rule::array => DBusValue::Vec(
+        Rule::value | Rule::dbus_value => convert_rule(input, rule.into_inner().next().unwrap()),
+        Rule::array | Rule::struct_t | Rule::dictionary => Ok(DBusValue::Vec(
             rule.into_inner()
-                .map(|inner_rule| convert_rule(inner_rule))
-                .collect_vec(),
-        ),
-        Rule::struct_t => DBusValue::Vec(
+                .map(|inner_rule| convert_rule(input, inner_rule))
+                .collect::<Result<Vec<DBusValue>, DBusError>>()?,
+        )),
+        Rule::BOOLEAN => Ok(DBusValue::Boolean(
+            rule.as_str().to_lowercase().parse().unwrap(),
+        )),
+        Rule::BYTE => Ok(DBusValue::Byte(
+            u8::from_str_radix(rule.as_str().trim_end_matches('y'), 16).unwrap(),
+        )),
+        Rule::INT_16 => parse_number(input, &rule, "n").map(DBusValue::Int16),
+        Rule::INT_32 => parse_number(input, &rule, "i").map(DBusValue::Int32),
+        Rule::INT_64 => parse_number(input, &rule, "x").map(DBusValue::Int64),
+        Rule::U_INT_16 => parse_number(input, &rule, "q").map(DBusValue::UInt16),
+        Rule::U_INT_32 => parse_number(input, &rule, "u").map(DBusValue::UInt32),
+        Rule::U_INT_64 => parse_number(input, &rule, "t").map(DBusValue::UInt64),
+        Rule::DOUBLE => parse_number(input, &rule, "d").map(DBusValue::Double),
+        // Without a type integers default to int32 and decimals to double.
+        Rule::NUMERAL if rule.as_str().contains(['.', 'e', 'E']) => {
+            parse_number(input, &rule, "d").map(DBusValue::Double)
+        }
+        Rule::NUMERAL => parse_number(input, &rule, "i").map(DBusValue::Int32),
+        Rule::FILE_DESCRIPTOR => parse_number(input, &rule, "h")
+            .map(FdSpec::Fd)
+            .map(DBusValue::UnixFd),
+        Rule::STRING => parse_string(input, rule).map(DBusValue::String),
+        Rule::OBJ_PATH => parse_object_path(input, rule),
+        Rule::TYPE_SIGNATURE => parse_signature(input, rule),
+        Rule::variant => {
+            let mut inner_rule = rule.into_inner();
+            let dbus_type = variant_type(input, inner_rule.next().unwrap())?;
+
+            Ok(DBusValue::Variant(
+                Box::new(dbus_type),
+                Box::new(convert_rule(input, inner_rule.next().unwrap())?),
+            ))
+        }
+        Rule::DOUBLE_QUOTED
+        | Rule::SINGLE_QUOTED
+        | Rule::BARE_WORDS
+        | Rule::ESCAPE
+        | Rule::TOKEN_END
+        | Rule::BARE_CHAR
+        | Rule::WORD_CHAR
+        | Rule::SIGNATURE
+        | Rule::EOI => unreachable!("Only converted as part of other rules"),
+    }
+}
+
+fn convert_typed_rule(
+    input: &str,
+    rule: Pair<Rule>,
+    dbus_type: &DBusType,
+) -> Result<DBusValue, DBusError> {
+    match (dbus_type, rule.as_rule()) {
+        (_, Rule::value | Rule::dbus_value) => {
+            convert_typed_rule(input, rule.into_inner().next().unwrap(), dbus_type)
+        }
+        (DBusType::Boolean, Rule::BOOLEAN)
+        | (DBusType::Byte, Rule::BYTE)
+        | (DBusType::Int16, Rule::INT_16)
+        | (DBusType::Int32, Rule::INT_32)
+        | (DBusType::Int64, Rule::INT_64)
+        | (DBusType::UInt16, Rule::U_INT_16)
+        | (DBusType::UInt32, Rule::U_INT_32)
+        | (DBusType::UInt64, Rule::U_INT_64)
+        | (DBusType::Double, Rule::DOUBLE)
+        | (DBusType::FileDescriptor, Rule::FILE_DESCRIPTOR)
+        | (DBusType::ObjPath, Rule::OBJ_PATH)
+        | (DBusType::Signature, Rule::TYPE_SIGNATURE) => convert_rule(input, rule),
+        (DBusType::Byte, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::Byte),
+        (DBusType::Int16, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::Int16),
+        (DBusType::Int32, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::Int32),
+        (DBusType::Int64, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::Int64),
+        (DBusType::UInt16, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::UInt16),
+        (DBusType::UInt32, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::UInt32),
+        (DBusType::UInt64, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::UInt64),
+        (DBusType::Double, Rule::NUMERAL) => parse_number(input, &rule, "").map(DBusValue::Double),
+        (DBusType::FileDescriptor, Rule::NUMERAL) if !rule.as_str().starts_with('-') => {
+            parse_number(input, &rule, "")
+                .map(FdSpec::Fd)
+                .map(DBusValue::UnixFd)
+        }
+        (DBusType::String, Rule::STRING) => parse_string(input, rule).map(DBusValue::String),
+        (DBusType::ObjPath, Rule::STRING) => parse_object_path(input, rule),
+        (DBusType::Signature, Rule::STRING) => parse_signature(input, rule),
+        (DBusType::FileDescriptor, Rule::STRING) => parse_fd(input, rule),
+        // Any other single token is taken literally as a string, e.g. `true` or `42`.
+        (
+            DBusType::String,
+            Rule::NUMERAL
+            | Rule::BOOLEAN
+            | Rule::BYTE
+            | Rule::INT_16
+            | Rule::INT_32
+            | Rule::INT_64
+            | Rule::U_INT_16
+            | Rule::U_INT_32
+            | Rule::U_INT_64
+            | Rule::DOUBLE
+            | Rule::FILE_DESCRIPTOR,
+        ) => Ok(DBusValue::String(rule.as_str().to_string())),
+        (DBusType::Struct(types), Rule::struct_t) => {
+            let span = rule.as_span();
+            let values = rule.into_inner().collect_vec();
+
+            if values.len() != types.len() {
+                return Err(DBusError::InvalidValue {
+                    value: input.to_string(),
+                    position: span.start(),
+                    message: format!(
+                        "Expected {} fields for type {} got: {}",
+                        types.len(),
+                        String::from(dbus_type),
+                        values.len()
+                    ),
+                });
+            }
+
+            Ok(DBusValue::Vec(
+                types
+                    .iter()
+                    .zip(values)
+                    .map(|(dbus_type, value)| convert_typed_rule(input, value, dbus_type))
+                    .collect::<Result<Vec<DBusValue>, DBusError>>()?,
+            ))
+        }
+        (DBusType::Array { value_type }, Rule::array) => Ok(DBusValue::Vec(
             rule.into_inner()
-                .map(|inner_rule| convert_rule(inner_rule))
-                .collect_vec(),
-        ),
-        Rule::dictionary => DBusValue::Vec(
+                .map(|value| convert_typed_rule(input, value, value_type))
+                .collect::<Result<Vec<DBusValue>, DBusError>>()?,
+        )),
+        (
+            DBusType::Dictionary {
+                key_type,
+                value_type,
+            },
+            Rule::dictionary,
+        ) => Ok(DBusValue::Vec(
             rule.into_inner()
-                .map(|inner_rule| convert_rule(inner_rule))
-                .collect_vec(),
-        ),
-        Rule::BOOLEAN => DBusValue::Boolean(rule.as_str().parse().unwrap()),
-        Rule::BYTE => {
-            DBusValue::Byte(u8::from_str_radix(rule.as_str().trim_end_matches("y"), 16).unwrap())
+                .tuples()
+                .flat_map(|(key, value)| {
+                    [
+                        convert_typed_rule(input, key, key_type),
+                        convert_typed_rule(input, value, value_type),
+                    ]
+                })
+                .collect::<Result<Vec<DBusValue>, DBusError>>()?,
+        )),
+        (DBusType::Variant, Rule::variant) => {
+            let mut inner_rule = rule.into_inner();
+            let dbus_type = variant_type(input, inner_rule.next().unwrap())?;
+            let dbus_value = convert_typed_rule(input, inner_rule.next().unwrap(), &dbus_type)?;
+
+            Ok(DBusValue::Variant(
+                Box::new(dbus_type),
+                Box::new(dbus_value),
+            ))
+        }
+        _ => Err(DBusError::InvalidValue {
+            value: input.to_string(),
+            position: rule.as_span().start(),
+            message: format!(
+                "Expected value of type {} got: {}",
+                String::from(dbus_type),
+                rule.as_str()
+            ),
+        }),
+    }
+}
+
+/// Removes the quotes of a string literal and resolves its escape sequences.
+fn parse_string(input: &str, rule: Pair<Rule>) -> Result<String, DBusError> {
+    let literal = rule.into_inner().next().unwrap();
+
+    if let Rule::BARE_WORDS = literal.as_rule() {
+        return Ok(literal.as_str().to_string());
+    }
+
+    let quoted = literal.as_str();
+    let start = literal.as_span().start() + 1;
+    let mut string = String::new();
+    let mut chars = quoted[1..quoted.len() - 1].char_indices();
+
+    while let Some((index, char)) = chars.next() {
+        if char != '\\' {
+            string.push(char);
+            continue;
+        }
+
+        // The grammar only accepts well-formed escapes.
+        match chars.next().map(|(_, char)| char) {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some('0') => string.push('\0'),
+            Some('u') => {
+                chars.next();
+
+                let code = chars
+                    .by_ref()
+                    .map(|(_, char)| char)
+                    .take_while(|char| *char != '}')
+                    .collect::<String>();
+
+                string.push(
+                    u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| DBusError::InvalidValue {
+                            value: input.to_string(),
+                            position: start + index,
+                            message: format!("\\u{{{}}} is not a valid unicode character", code),
+                        })?,
+                );
+            }
+            Some(char) => string.push(char),
+            None => unreachable!("Escapes are never at the end of a quoted string"),
         }
-        Rule::INT_16 => DBusValue::Int16(rule.as_str().trim_end_matches("n").parse().unwrap()),
-        Rule::INT_32 => DBusValue::Int32(rule.as_str().trim_end_matches("i").parse().unwrap()),
-        Rule::INT_64 => DBusValue::Int64(rule.as_str().trim_end_matches("x").parse().unwrap()),
-        Rule::U_INT_16 => DBusValue::UInt16(rule.as_str().trim_end_matches("q").parse().unwrap()),
-        Rule::U_INT_32 => DBusValue::UInt32(rule.as_str().trim_end_matches("u").parse().unwrap()),
-        Rule::U_INT_64 => DBusValue::UInt64(rule.as_str().trim_end_matches("t").parse().unwrap()),
-        Rule::DOUBLE => DBusValue::Double(rule.as_str().trim_end_matches("d").parse().unwrap()),
-        Rule::STRING => DBusValue::String(rule.as_str().replace("\"", "").to_string()),
     }
+
+    Ok(string)
+}
+
+/// Parses a (possibly prefixed) string literal as an object path.
+fn parse_object_path(input: &str, rule: Pair<Rule>) -> Result<DBusValue, DBusError> {
+    let position = rule.as_span().start();
+    let path = parse_string(input, rule)?;
+
+    validate_object_path(&path).map_err(|message| DBusError::InvalidValue {
+        value: input.to_string(),
+        position,
+        message,
+    })?;
+
+    Ok(DBusValue::ObjectPath(path))
+}
+
+/// Parses a (possibly prefixed) string literal as a signature.
+fn parse_signature(input: &str, rule: Pair<Rule>) -> Result<DBusValue, DBusError> {
+    let position = rule.as_span().start();
+    let signature = parse_string(input, rule)?;
+
+    validate_signature(&signature).map_err(|message| DBusError::InvalidValue {
+        value: input.to_string(),
+        position,
+        message,
+    })?;
+
+    Ok(DBusValue::Signature(signature))
+}
+
+/// Parses a string literal like `'fd:3'` as a file descriptor, see [`FdSpec::parse`].
+fn parse_fd(input: &str, rule: Pair<Rule>) -> Result<DBusValue, DBusError> {
+    let position = rule.as_span().start();
+    let spec = parse_string(input, rule)?;
+
+    FdSpec::parse(&spec)
+        .unwrap_or_else(|| Err(format!("Expected fd:N, file:PATH or stdin got: {}", spec)))
+        .map(DBusValue::UnixFd)
+        .map_err(|message| DBusError::InvalidValue {
+            value: input.to_string(),
+            position,
+            message,
+        })
+}
+
+/// Checks `path` against the object path syntax of the D-Bus specification: `/` or `/`
+/// separated, non-empty elements of `[A-Za-z0-9_]` without a trailing `/`.
+pub fn validate_object_path(path: &str) -> Result<(), String> {
+    if path == "/" {
+        return Ok(());
+    }
+
+    match path.strip_prefix('/') {
+        None => Err(format!("Object path must start with '/': {}", path)),
+        Some(elements) => elements.split('/').try_for_each(|element| {
+            if element.is_empty() {
+                Err(format!("Object path contains an empty element: {}", path))
+            } else if let Some(char) = element
+                .chars()
+                .find(|char| !(char.is_ascii_alphanumeric() || *char == '_'))
+            {
+                Err(format!(
+                    "Object path contains invalid character '{}': {}",
+                    char, path
+                ))
+            } else {
+                Ok(())
+            }
+        }),
+    }
+}
+
+/// Checks that `signature` is a valid sequence of complete types, see [`DBusType::parse`].
+pub fn validate_signature(signature: &str) -> Result<(), String> {
+    DBusType::parse(signature)
+        .map(|_| ())
+        .map_err(|err| match err {
+            DBusError::InvalidSignature {
+                position, message, ..
+            } => format!(
+                "Invalid signature {} at position {}: {}",
+                signature, position, message
+            ),
+            err => err.to_string(),
+        })
+}
+
+/// Parses the signature of a variant literal, errors point into the whole `input`.
+fn variant_type(input: &str, signature: Pair<Rule>) -> Result<DBusType, DBusError> {
+    DBusType::try_from(signature.as_str()).map_err(|err| match err {
+        DBusError::InvalidSignature {
+            position, message, ..
+        } => DBusError::InvalidValue {
+            value: input.to_string(),
+            position: signature.as_span().start() + position,
+            message,
+        },
+        err => err,
+    })
+}
+
+fn parse_number<T: FromStr>(input: &str, rule: &Pair<Rule>, suffix: &str) -> Result<T, DBusError> {
+    let number = rule.as_str().trim_end_matches(suffix);
+
+    number.parse().map_err(|_| DBusError::InvalidValue {
+        value: input.to_string(),
+        position: rule.as_span().start(),
+        message: if number.parse::<i128>().is_ok() {
+            format!("{} is out of range", rule.as_str())
+        } else {
+            format!("{} is not a valid {}", rule.as_str(), type_name::<T>())
+        },
+    })
 }
 
 #[cfg(test)]
 mod test {
-    use crate::dbus_value::DBusValue;
+    use dbus::arg::messageitem::MessageItem;
+
+    use crate::{
+        dbus_error::DBusError,
+        dbus_fd::FdSpec,
+        dbus_type::DBusType,
+        dbus_value::{validate_object_path, validate_signature, DBusValue},
+    };
 
     #[test]
     fn test_parse() {
-        let value: DBusValue = "{ \"test\": -8i }".into();
+        let value = DBusValue::try_from("{ \"test\": -8i }");
         assert_eq!(
             value,
-            DBusValue::Vec(vec![
+            Ok(DBusValue::Vec(vec![
                 DBusValue::String("test".to_string()),
                 DBusValue::Int32(-8)
-            ])
+            ]))
         );
 
-        assert_eq!(Into::<DBusValue>::into("-1.9d"), DBusValue::Double(-1.9));
-        assert_eq!(Into::<DBusValue>::into("ffy"), DBusValue::Byte(255u8));
-        assert_eq!(Into::<DBusValue>::into("fey"), DBusValue::Byte(254u8));
+        assert_eq!(DBusValue::try_from("-1.9d"), Ok(DBusValue::Double(-1.9)));
+        assert_eq!(DBusValue::try_from("ffy"), Ok(DBusValue::Byte(255u8)));
+        assert_eq!(DBusValue::try_from("fey"), Ok(DBusValue::Byte(254u8)));
+        assert_eq!(DBusValue::try_from("TRUE"), Ok(DBusValue::Boolean(true)));
         assert_eq!(
-            Into::<DBusValue>::into(
-                "launch-new-instance@gnome-shell-extensions.gcampax.github.com"
-            ),
-            DBusValue::String(
+            DBusValue::try_from("launch-new-instance@gnome-shell-extensions.gcampax.github.com"),
+            Ok(DBusValue::String(
                 "launch-new-instance@gnome-shell-extensions.gcampax.github.com".into()
-            )
+            ))
         );
         assert_eq!(
-            Into::<DBusValue>::into(
-                "(8i,some@string)"
-            ),
-            DBusValue::Vec(
-                vec![
-                    DBusValue::Int32(8),
-                    DBusValue::String("some@string".into())
-                ]
-            )
+            DBusValue::try_from("(8i,some@string)"),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::Int32(8),
+                DBusValue::String("some@string".into())
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            DBusValue::try_from("(8i, 300q,"),
+            Err(DBusError::InvalidValue { position: 10, .. })
+        ));
+        assert!(matches!(
+            DBusValue::try_from("[1i, 99999999999i]"),
+            Err(DBusError::InvalidValue { position: 5, .. })
+        ));
+        assert!(matches!(
+            DBusValue::try_from("<az 1i>"),
+            Err(DBusError::InvalidValue { position: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        assert_eq!(
+            DBusValue::try_from("\"a \\\"quoted\\\" string\""),
+            Ok(DBusValue::String("a \"quoted\" string".into()))
+        );
+        assert_eq!(
+            DBusValue::try_from("'single, quoted: string'"),
+            Ok(DBusValue::String("single, quoted: string".into()))
+        );
+        assert_eq!(
+            DBusValue::try_from("'tab\\there\\nnew line \\u{e9}\\\\'"),
+            Ok(DBusValue::String("tab\there\nnew line \u{e9}\\".into()))
+        );
+        assert_eq!(DBusValue::try_from("''"), Ok(DBusValue::String("".into())));
+        assert_eq!(
+            DBusValue::try_from("/org/freedesktop/DBus"),
+            Ok(DBusValue::String("/org/freedesktop/DBus".into()))
+        );
+        assert_eq!(
+            DBusValue::parse(":1.42", &DBusType::String),
+            Ok(DBusValue::String(":1.42".into()))
+        );
+        assert_eq!(
+            DBusValue::try_from("{url: http://x:80/a, ':1.42': unix:path=/run/x}"),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::String("url".into()),
+                DBusValue::String("http://x:80/a".into()),
+                DBusValue::String(":1.42".into()),
+                DBusValue::String("unix:path=/run/x".into())
+            ]))
+        );
+        assert_eq!(
+            DBusValue::try_from("[two words,  8inch, truely]"),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::String("two words".into()),
+                DBusValue::String("8inch".into()),
+                DBusValue::String("truely".into())
+            ]))
+        );
+        assert!(matches!(
+            DBusValue::try_from("'\\u{d800}'"),
+            Err(DBusError::InvalidValue { position: 1, .. })
+        ));
+        assert!(matches!(
+            DBusValue::try_from("'\\q'"),
+            Err(DBusError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            DBusValue::try_from("\"unterminated"),
+            Err(DBusError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_typed() {
+        let parse = |input: &str, signature: &str| {
+            DBusValue::parse(input, &DBusType::try_from(signature).unwrap())
+        };
+
+        assert_eq!(parse("8", "i"), Ok(DBusValue::Int32(8)));
+        assert_eq!(parse("8", "t"), Ok(DBusValue::UInt64(8)));
+        assert_eq!(parse("8", "d"), Ok(DBusValue::Double(8.0)));
+        assert_eq!(parse("-1.5e3", "d"), Ok(DBusValue::Double(-1500.0)));
+        assert_eq!(parse("255", "y"), Ok(DBusValue::Byte(255)));
+        assert_eq!(parse("8u", "u"), Ok(DBusValue::UInt32(8)));
+        assert_eq!(parse("42", "s"), Ok(DBusValue::String("42".into())));
+        assert_eq!(parse("", "s"), Ok(DBusValue::String("".into())));
+        assert_eq!(
+            parse("/org/foo", "o"),
+            Ok(DBusValue::ObjectPath("/org/foo".into()))
+        );
+        assert_eq!(parse("as", "g"), Ok(DBusValue::Signature("as".into())));
+        assert_eq!(
+            parse("'a{sv}'", "g"),
+            Ok(DBusValue::Signature("a{sv}".into()))
+        );
+        assert_eq!(parse("3", "h"), Ok(DBusValue::UnixFd(FdSpec::Fd(3))));
+        assert_eq!(
+            parse("(1, [2, 3], {a: <q 4>})", "(naya{sv})"),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::Int16(1),
+                DBusValue::Vec(vec![DBusValue::Byte(2), DBusValue::Byte(3)]),
+                DBusValue::Vec(vec![
+                    DBusValue::String("a".into()),
+                    DBusValue::Variant(Box::new(DBusType::UInt16), Box::new(DBusValue::UInt16(4)))
+                ])
+            ]))
+        );
+        assert_eq!(DBusValue::try_from("8"), Ok(DBusValue::Int32(8)));
+        assert_eq!(DBusValue::try_from("8.5"), Ok(DBusValue::Double(8.5)));
+    }
+
+    #[test]
+    fn test_parse_typed_invalid() {
+        let parse = |input: &str, signature: &str| {
+            DBusValue::parse(input, &DBusType::try_from(signature).unwrap())
+        };
+
+        assert!(matches!(
+            parse("[1, 256]", "ay"),
+            Err(DBusError::InvalidValue { position: 4, message, .. }) if message == "256 is out of range"
+        ));
+        assert!(matches!(
+            parse("-1", "u"),
+            Err(DBusError::InvalidValue { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse("1.5", "i"),
+            Err(DBusError::InvalidValue { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse("8i", "u"),
+            Err(DBusError::InvalidValue { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse("(1, 2)", "(iii)"),
+            Err(DBusError::InvalidValue { position: 0, .. })
+        ));
+        assert!(matches!(
+            parse("{a: [1]}", "a{ss}"),
+            Err(DBusError::InvalidValue { position: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_paths_signatures_and_fds() {
+        assert_eq!(
+            DBusValue::try_from("o\"/org/freedesktop/DBus\""),
+            Ok(DBusValue::ObjectPath("/org/freedesktop/DBus".into()))
+        );
+        assert_eq!(
+            DBusValue::try_from("[o'/', g'', g'a{sv}(ii)', 4h]"),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::ObjectPath("/".into()),
+                DBusValue::Signature("".into()),
+                DBusValue::Signature("a{sv}(ii)".into()),
+                DBusValue::UnixFd(FdSpec::Fd(4))
+            ]))
+        );
+        assert!(matches!(
+            DBusValue::try_from("(1i, o'/org/')"),
+            Err(DBusError::InvalidValue { position: 5, .. })
+        ));
+        assert!(matches!(
+            DBusValue::try_from("g'(sx'"),
+            Err(DBusError::InvalidValue { position: 0, .. })
+        ));
+
+        ["/org//foo", "org/foo", "/org/foo-bar", ""]
+            .iter()
+            .for_each(|path| assert!(validate_object_path(path).is_err(), "{}", path));
+        ["/", "/org/foo_1/Bar"]
+            .iter()
+            .for_each(|path| assert!(validate_object_path(path).is_ok(), "{}", path));
+        assert!(validate_signature(&"i".repeat(256)).is_err());
+        assert!(validate_signature("sa{sv}as").is_ok());
+    }
+
+    #[test]
+    fn test_from_message_item() {
+        let item = MessageItem::Struct(vec![
+            MessageItem::Str("test".into()),
+            MessageItem::Variant(Box::new(MessageItem::UInt32(8))),
+            MessageItem::new_dict(vec![(
+                MessageItem::Str("key".into()),
+                MessageItem::ObjectPath("/org/test".into()),
+            )])
+            .unwrap(),
+        ]);
+
+        assert_eq!(
+            DBusValue::try_from(&item),
+            Ok(DBusValue::Vec(vec![
+                DBusValue::String("test".into()),
+                DBusValue::Variant(Box::new(DBusType::UInt32), Box::new(DBusValue::UInt32(8))),
+                DBusValue::Vec(vec![
+                    DBusValue::String("key".into()),
+                    DBusValue::ObjectPath("/org/test".into())
+                ])
+            ]))
         );
     }
 }
@@ -0,0 +1,517 @@
+use itertools::Itertools;
+use log::debug;
+use serde_json::{json, Value};
+use xml::{attribute::OwnedAttribute, reader::XmlEvent, EventReader};
+
+use crate::{dbus_error::DBusError, dbus_type::DBusType};
+
+/// A direct child of an introspected `<node>`.
+#[derive(Debug)]
+pub enum Entry {
+    /// A child object, `name` is relative to the introspected path.
+    Node { name: String },
+    Interface {
+        name: String,
+        methods: Vec<Method>,
+        signals: Vec<Signal>,
+        properties: Vec<Property>,
+        annotations: Vec<Annotation>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Method {
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Method {
+    /// A method described by a signature instead of introspection data, arguments are unnamed.
+    pub fn from_signature(name: &str, signature: &str) -> Result<Method, DBusError> {
+        let args = DBusType::parse(signature)?
+            .iter()
+            .map(|dbus_type| Argument {
+                name: String::new(),
+                typ: dbus_type.into(),
+                direction: Some("in".into()),
+                annotations: Vec::new(),
+            })
+            .collect_vec();
+
+        Ok(Method {
+            name: name.to_string(),
+            args,
+            annotations: Vec::new(),
+        })
+    }
+
+    /// Arguments without a direction are in-arguments.
+    pub fn inputs(&self) -> impl Iterator<Item = &Argument> {
+        self.args
+            .iter()
+            .filter(|arg| arg.direction.as_deref().unwrap_or("in") == "in")
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &Argument> {
+        self.args
+            .iter()
+            .filter(|arg| arg.direction.as_deref() == Some("out"))
+    }
+
+    /// A usage line naming the in-arguments and their types.
+    pub fn usage(&self) -> String {
+        format!(
+            "Usage: {} {}",
+            self.name,
+            self.inputs()
+                .map(|arg| if arg.name.is_empty() {
+                    format!("<{}>", arg.typ)
+                } else {
+                    format!("<{}: {}>", arg.name, arg.typ)
+                })
+                .join(" ")
+        )
+    }
+
+    /// Whether the method is annotated with `org.freedesktop.DBus.Method.NoReply`.
+    pub fn is_no_reply(&self) -> bool {
+        self.annotations.iter().any(|annotation| {
+            annotation.name == "org.freedesktop.DBus.Method.NoReply" && annotation.value == "true"
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Signal {
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug)]
+pub struct Argument {
+    /// Empty for unnamed arguments.
+    pub name: String,
+    pub typ: String,
+    /// `in` or `out`, arguments of signals have no direction.
+    pub direction: Option<String>,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug)]
+pub struct Property {
+    pub name: String,
+    pub typ: String,
+    /// `read`, `write` or `readwrite`.
+    pub access: String,
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug)]
+pub struct Annotation {
+    pub name: String,
+    pub value: String,
+}
+
+/// An object of a service and its descendants, see [`Client::tree`](crate::client::Client::tree).
+#[derive(Debug)]
+pub struct Object {
+    pub path: String,
+    pub interfaces: Vec<String>,
+    /// Set if the object could not be introspected.
+    pub error: Option<DBusError>,
+    pub children: Vec<Object>,
+}
+
+impl Object {
+    /// Keeps the objects implementing `interface_name` and their ancestors, returns whether any
+    /// is left. The objects keep all their interfaces.
+    pub fn filter(&mut self, interface_name: &str) -> bool {
+        self.children
+            .retain_mut(|child| child.filter(interface_name));
+
+        self.interfaces.iter().any(|name| name == interface_name) || !self.children.is_empty()
+    }
+}
+
+impl From<&Method> for Value {
+    fn from(method: &Method) -> Self {
+        json!({
+            "name": method.name,
+            "args": method.args.iter().map(Into::<Value>::into).collect_vec(),
+            "annotations": annotations_json(&method.annotations),
+        })
+    }
+}
+
+impl From<&Signal> for Value {
+    fn from(signal: &Signal) -> Self {
+        json!({
+            "name": signal.name,
+            "args": signal.args.iter().map(Into::<Value>::into).collect_vec(),
+            "annotations": annotations_json(&signal.annotations),
+        })
+    }
+}
+
+impl From<&Property> for Value {
+    fn from(property: &Property) -> Self {
+        json!({
+            "name": property.name,
+            "type": property.typ,
+            "access": property.access,
+            "annotations": annotations_json(&property.annotations),
+        })
+    }
+}
+
+impl From<&Object> for Value {
+    fn from(object: &Object) -> Self {
+        json!({
+            "path": object.path,
+            "interfaces": object.interfaces,
+            "error": object.error.as_ref().map(|err| err.to_string()),
+            "children": object.children.iter().map(Into::<Value>::into).collect_vec(),
+        })
+    }
+}
+
+impl From<&Argument> for Value {
+    fn from(argument: &Argument) -> Self {
+        json!({
+            "name": argument.name,
+            "type": argument.typ,
+            "direction": argument.direction,
+            "annotations": annotations_json(&argument.annotations),
+        })
+    }
+}
+
+/// Maps annotation names to their values.
+pub fn annotations_json(annotations: &[Annotation]) -> Value {
+    Value::Object(
+        annotations
+            .iter()
+            .map(|annotation| (annotation.name.clone(), json!(annotation.value)))
+            .collect(),
+    )
+}
+
+/// Parses introspection data, members are only attached to the element they are nested in.
+pub fn parse_entries(xml: &str) -> Result<Vec<Entry>, DBusError> {
+    let mut entries = Vec::new();
+    let mut elements: Vec<String> = Vec::new();
+    let parser = EventReader::from_str(xml);
+
+    for e in parser {
+        match e {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) => {
+                debug!("<{}> in {:?}", name.local_name, elements);
+
+                match (elements.as_slice(), name.local_name.as_str()) {
+                    ([_], "node") => {
+                        if let Some(attribute) = attributes.get("name") {
+                            entries.push(Entry::Node {
+                                name: attribute.value.clone(),
+                            })
+                        }
+                    }
+                    ([_], "interface") => entries.push(Entry::Interface {
+                        name: required(&attributes, "interface", "name")?,
+                        methods: Vec::new(),
+                        signals: Vec::new(),
+                        properties: Vec::new(),
+                        annotations: Vec::new(),
+                    }),
+                    ([_, _], "method") => {
+                        if let Some(Entry::Interface { methods, .. }) = entries.last_mut() {
+                            methods.push(Method {
+                                name: required(&attributes, "method", "name")?,
+                                args: Vec::new(),
+                                annotations: Vec::new(),
+                            });
+                        }
+                    }
+                    ([_, _], "signal") => {
+                        if let Some(Entry::Interface { signals, .. }) = entries.last_mut() {
+                            signals.push(Signal {
+                                name: required(&attributes, "signal", "name")?,
+                                args: Vec::new(),
+                                annotations: Vec::new(),
+                            });
+                        }
+                    }
+                    ([_, _], "property") => {
+                        if let Some(Entry::Interface { properties, .. }) = entries.last_mut() {
+                            properties.push(Property {
+                                name: required(&attributes, "property", "name")?,
+                                typ: required(&attributes, "property", "type")?,
+                                access: required(&attributes, "property", "access")?,
+                                annotations: Vec::new(),
+                            });
+                        }
+                    }
+                    ([_, _, parent], "arg") => {
+                        let argument = Argument {
+                            name: attributes
+                                .get("name")
+                                .map(|attribute| attribute.value.clone())
+                                .unwrap_or("".into()),
+                            typ: required(&attributes, "arg", "type")?,
+                            direction: attributes
+                                .get("direction")
+                                .map(|direction| direction.value.clone()),
+                            annotations: Vec::new(),
+                        };
+
+                        if let Some(Entry::Interface {
+                            methods, signals, ..
+                        }) = entries.last_mut()
+                        {
+                            match parent.as_str() {
+                                "method" => {
+                                    if let Some(method) = methods.last_mut() {
+                                        method.args.push(argument);
+                                    }
+                                }
+                                "signal" => {
+                                    if let Some(signal) = signals.last_mut() {
+                                        signal.args.push(argument);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    (_, "annotation") => {
+                        let annotation = Annotation {
+                            name: required(&attributes, "annotation", "name")?,
+                            value: required(&attributes, "annotation", "value")?,
+                        };
+
+                        if let Some(Entry::Interface {
+                            methods,
+                            signals,
+                            properties,
+                            annotations,
+                            ..
+                        }) = entries.last_mut()
+                        {
+                            let annotations = match elements
+                                .iter()
+                                .map(String::as_str)
+                                .collect_vec()
+                                .as_slice()
+                            {
+                                [_, "interface"] => Some(annotations),
+                                [_, _, "method"] => {
+                                    methods.last_mut().map(|method| &mut method.annotations)
+                                }
+                                [_, _, "signal"] => {
+                                    signals.last_mut().map(|signal| &mut signal.annotations)
+                                }
+                                [_, _, "property"] => properties
+                                    .last_mut()
+                                    .map(|property| &mut property.annotations),
+                                [_, _, "method", "arg"] => methods
+                                    .last_mut()
+                                    .and_then(|method| method.args.last_mut())
+                                    .map(|arg| &mut arg.annotations),
+                                [_, _, "signal", "arg"] => signals
+                                    .last_mut()
+                                    .and_then(|signal| signal.args.last_mut())
+                                    .map(|arg| &mut arg.annotations),
+                                _ => None,
+                            };
+
+                            if let Some(annotations) = annotations {
+                                annotations.push(annotation);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                elements.push(name.local_name);
+            }
+            Ok(XmlEvent::EndElement { .. }) => {
+                elements.pop();
+            }
+            Ok(_) => {}
+            Err(err) => return Err(DBusError::Introspection(err.to_string())),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn required(
+    attributes: &Vec<OwnedAttribute>,
+    element: &str,
+    name: &str,
+) -> Result<String, DBusError> {
+    attributes
+        .get(name)
+        .map(|attribute| attribute.value.clone())
+        .ok_or_else(|| {
+            DBusError::Introspection(format!("Missing attribute {} on <{}>", name, element))
+        })
+}
+
+trait Gettable {
+    fn get(&self, name: &str) -> Option<&OwnedAttribute>;
+}
+
+impl Gettable for Vec<OwnedAttribute> {
+    fn get(&self, name: &str) -> Option<&OwnedAttribute> {
+        find_attribute(self, &name.into())
+    }
+}
+
+fn find_attribute<'l>(attrs: &'l [OwnedAttribute], name: &String) -> Option<&'l OwnedAttribute> {
+    attrs.iter().find(|attr| attr.name.local_name.eq(name))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        dbus_error::DBusError,
+        introspection::{parse_entries, Annotation, Entry, Method, Object},
+    };
+
+    #[test]
+    fn test_parse_entries() {
+        let entries = parse_entries(
+            r#"<node name="/org/example">
+                <interface name="org.example.Test">
+                    <signal name="Changed">
+                        <arg name="value" type="s"/>
+                    </signal>
+                    <annotation name="org.freedesktop.DBus.Deprecated" value="true"/>
+                    <method name="Echo">
+                        <annotation name="org.freedesktop.DBus.Method.NoReply" value="true"/>
+                        <arg name="input" type="i" direction="in">
+                            <annotation name="org.example.Hint" value="count"/>
+                        </arg>
+                        <arg name="output" type="i" direction="out"/>
+                    </method>
+                    <property name="Count" type="u" access="read"/>
+                </interface>
+                <node name="child">
+                    <interface name="org.example.Child"/>
+                </node>
+            </node>"#,
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+
+        if let Entry::Interface {
+            name,
+            methods,
+            signals,
+            properties,
+            annotations,
+        } = &entries[0]
+        {
+            assert_eq!(name, "org.example.Test");
+            assert_eq!(annotations[0].name, "org.freedesktop.DBus.Deprecated");
+            assert_eq!(methods.len(), 1);
+            assert_eq!(methods[0].name, "Echo");
+            assert!(methods[0].is_no_reply());
+            assert_eq!(methods[0].args.len(), 2);
+            assert_eq!(methods[0].args[0].annotations[0].value, "count");
+            assert_eq!(signals.len(), 1);
+            assert_eq!(signals[0].name, "Changed");
+            assert_eq!(signals[0].args[0].typ, "s");
+            assert_eq!(properties[0].name, "Count");
+        } else {
+            panic!("Expected interface got: {:?}", entries[0]);
+        }
+
+        assert!(matches!(&entries[1], Entry::Node { name } if name == "child"));
+    }
+
+    #[test]
+    fn test_parse_entries_invalid() {
+        let err = parse_entries(r#"<node><interface name="org.example.Test"></node>"#).unwrap_err();
+
+        assert!(matches!(err, DBusError::Introspection(_)));
+        assert_eq!(
+            err.to_string()
+                .matches("Invalid introspection data")
+                .count(),
+            1
+        );
+        assert!(matches!(
+            parse_entries(r#"<node><method name="Echo"/>"#),
+            Err(DBusError::Introspection(_))
+        ));
+    }
+
+    #[test]
+    fn test_filter() {
+        let object = |path: &str, interfaces: &[&str], children| Object {
+            path: path.into(),
+            interfaces: interfaces.iter().map(|name| name.to_string()).collect(),
+            error: None,
+            children,
+        };
+        let mut root = object(
+            "/",
+            &["org.example.Root"],
+            vec![
+                object(
+                    "/a",
+                    &[],
+                    vec![object(
+                        "/a/b",
+                        &["org.example.Item", "org.example.Other"],
+                        vec![],
+                    )],
+                ),
+                object("/c", &["org.example.Other"], vec![]),
+                object("/d", &["org.example.Item"], vec![]),
+            ],
+        );
+
+        assert!(root.filter("org.example.Item"));
+        assert_eq!(root.interfaces, vec!["org.example.Root"]);
+        assert_eq!(
+            root.children
+                .iter()
+                .map(|child| child.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/a", "/d"]
+        );
+        assert_eq!(
+            root.children[0].children[0].interfaces,
+            vec!["org.example.Item", "org.example.Other"]
+        );
+        assert!(!root.filter("org.example.Missing"));
+    }
+
+    #[test]
+    fn test_is_no_reply() {
+        let method = |annotations: &[(&str, &str)]| Method {
+            name: "Notify".into(),
+            args: Vec::new(),
+            annotations: annotations
+                .iter()
+                .map(|(name, value)| Annotation {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        };
+
+        assert!(method(&[("org.freedesktop.DBus.Method.NoReply", "true")]).is_no_reply());
+        assert!(!method(&[("org.freedesktop.DBus.Method.NoReply", "false")]).is_no_reply());
+        assert!(!method(&[("org.freedesktop.DBus.Deprecated", "true")]).is_no_reply());
+        assert!(!method(&[]).is_no_reply());
+        assert!(!Method::from_signature("Notify", "s").unwrap().is_no_reply());
+    }
+}
@@ -0,0 +1,48 @@
+//! A D-Bus client library and the `dbus-client` command line tool built on it.
+//!
+//! - [`DBusType`] models signatures and [`DBusValue`] values, [`DBusValue::parse`] reads the
+//!   literal syntax of the command line guided by a type.
+//! - [`DBusArgument`] pairs a type with a value to validate it, render it as a literal or JSON
+//!   and convert it into a `MessageItem`.
+//! - [`introspection`] models introspection data and parses its XML.
+//! - [`Client`] is a blocking connection with calls, properties, introspection and monitoring.
+//! - With the `serde` feature `DBusType` and `DBusValue` implement `Serialize` and
+//!   `Deserialize` and `dbus_serde::TypedValue` reads plain data guided by a type.
+//!
+//! ```no_run
+//! use dbus_client::{client::new_method_call, Client, DBusArgument, DBusType, DBusValue};
+//!
+//! let client = Client::connect("session", Default::default())?;
+//! let dbus_type = DBusType::Struct(vec![DBusType::String]);
+//! let dbus_value = DBusValue::parse("(org.freedesktop.DBus)", &dbus_type)?;
+//! let reply = client.call(
+//!     new_method_call(
+//!         "org.freedesktop.DBus",
+//!         "/org/freedesktop/DBus",
+//!         "org.freedesktop.DBus",
+//!         "GetNameOwner",
+//!     )?,
+//!     DBusArgument {
+//!         dbus_type: &dbus_type,
+//!         dbus_value: &dbus_value,
+//!     },
+//! )?;
+//! # Ok::<(), dbus_client::DBusError>(())
+//! ```
+
+pub mod client;
+pub mod dbus_address;
+pub mod dbus_argument;
+pub mod dbus_error;
+pub mod dbus_fd;
+#[cfg(feature = "serde")]
+pub mod dbus_serde;
+pub mod dbus_type;
+pub mod dbus_value;
+pub mod introspection;
+
+pub use client::Client;
+pub use dbus_argument::DBusArgument;
+pub use dbus_error::DBusError;
+pub use dbus_type::DBusType;
+pub use dbus_value::DBusValue;
@@ -1,33 +1,33 @@
-use std::{convert::Infallible, ops::Not, time::Duration};
+mod completion;
+mod shell;
 
-use clap::{App, Arg, SubCommand, Values};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    process,
+    time::Duration,
+};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
 use dbus::{
-    arg::messageitem::{MessageItem, MessageItemArray, MessageItemDict},
-    blocking::Connection,
-    channel::Channel,
-    strings::Signature,
-    Message,
+    arg::messageitem::MessageItem,
+    message::MatchRule,
+    strings::{BusName, Interface, Member, Path},
+    Message, MessageType,
+};
+use dbus_client::{
+    client::{arguments_type, new_method_call, Options, TIMEOUT_INFINITE},
+    dbus_fd,
+    introspection::{annotations_json, Annotation, Argument, Entry, Method, Object, Property},
+    Client, DBusArgument, DBusError, DBusType, DBusValue,
 };
-use dbus_type::DBusType;
-use dbus_value::DBusValue;
 use itertools::Itertools;
-use log::{debug, info, warn, LevelFilter};
+use log::LevelFilter;
+use serde_json::{json, Value};
 use simple_logger::SimpleLogger;
-use xml::{
-    attribute::OwnedAttribute,
-    reader::{Error, XmlEvent},
-    EventReader,
-};
 
-use crate::dbus_argument::DBusArgument;
-
-mod dbus_argument;
-mod dbus_error;
-mod dbus_type;
-mod dbus_value;
-
-fn main() {
-    let app = App::new("Dbus client for Introspection")
+fn app() -> App<'static, 'static> {
+    App::new("Dbus client for Introspection")
         .version("0.1.0")
         .author("Felix M. <fmarezki@gmail.com>")
         .about("Interact with dbus")
@@ -52,7 +52,41 @@ fn main() {
                 ),
         )
         .subcommand(
-            SubCommand::with_name("call")
+            SubCommand::with_name("tree")
+                .about("Recursively introspect all objects of a service")
+                .alias("t")
+                .arg(
+                    Arg::with_name("bus-name")
+                        .required(true)
+                        .help("Name of the bus"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .default_value("/")
+                        .help("Path of the object to start from"),
+                )
+                .arg(
+                    Arg::with_name("max-depth")
+                        .long("max-depth")
+                        .takes_value(true)
+                        .validator(|depth| {
+                            depth
+                                .parse::<usize>()
+                                .map(|_| ())
+                                .map_err(|err| err.to_string())
+                        })
+                        .help("Do not descend more than this many levels below the path"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .long("interface")
+                        .takes_value(true)
+                        .help("Only show objects implementing this interface"),
+                ),
+        )
+        .subcommand(
+            with_json_args(
+                SubCommand::with_name("call")
                 .about("Call a method on an interface")
                 .alias("c")
                 .arg(
@@ -75,7 +109,236 @@ fn main() {
                     Arg::with_name("argument")
                         .takes_value(true)
                         .required(false)
-                        .help("Argument passed to the method call"),
+                        .multiple(true)
+                        .help("Arguments passed to the method call, one per in-argument"),
+                )
+                .arg(
+                    Arg::with_name("arg")
+                        .long("arg")
+                        .value_name("NAME=VALUE")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Argument passed by the name of the in-argument"),
+                )
+                .arg(
+                    Arg::with_name("signature")
+                        .long("signature")
+                        .takes_value(true)
+                        .help("Signature of the method arguments, skips introspection"),
+                ),
+                &["argument", "arg"],
+                "Arguments as a JSON array with one element per in-argument",
+            )
+                .arg(
+                    Arg::with_name("fd-output")
+                        .long("fd-output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("Copy the contents of file descriptors in the reply to FILE, - for stdout"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("call-raw")
+                .about("Call a method with an explicit signature without introspecting")
+                .arg(
+                    Arg::with_name("bus-name")
+                        .required(true)
+                        .help("Name of the bus"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .help("Path of the object"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .required(true)
+                        .help("Interface name"),
+                )
+                .arg(Arg::with_name("method").required(true).help("Method name"))
+                .arg(
+                    Arg::with_name("signature")
+                        .required(false)
+                        .help("Signature of the method arguments, e.g. si"),
+                )
+                .arg(
+                    Arg::with_name("argument")
+                        .required(false)
+                        .requires("signature")
+                        .help("Arguments of the method as a struct, e.g. (hello, 8i)"),
+                )
+                .arg(
+                    Arg::with_name("fd-output")
+                        .long("fd-output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("Copy the contents of file descriptors in the reply to FILE, - for stdout"),
+                ),
+        )
+        .subcommand(
+            with_json_args(
+                SubCommand::with_name("emit")
+                .about("Emit a signal")
+                .alias("e")
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .help("Path of the emitting object"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .required(true)
+                        .help("Interface name"),
+                )
+                .arg(Arg::with_name("member").required(true).help("Signal name"))
+                .arg(
+                    Arg::with_name("signature")
+                        .required(false)
+                        .help("Signature of the signal arguments, e.g. si"),
+                )
+                .arg(
+                    Arg::with_name("argument")
+                        .required(false)
+                        .requires("signature")
+                        .help("Arguments of the signal as a struct, e.g. (hello, 8i)"),
+                ),
+                &["argument"],
+                "Arguments as a JSON array with one element per type of the signature",
+            )
+                .arg(
+                    Arg::with_name("destination")
+                        .long("destination")
+                        .takes_value(true)
+                        .help("Send the signal to this bus name only"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("get")
+                .about("Get a property of an interface")
+                .arg(
+                    Arg::with_name("bus-name")
+                        .required(true)
+                        .help("Name of the bus"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .help("Path of the object"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .required(true)
+                        .help("Interface name"),
+                )
+                .arg(
+                    Arg::with_name("property")
+                        .required(true)
+                        .help("Property name"),
+                ),
+        )
+        .subcommand(
+            with_json_args(
+                SubCommand::with_name("set")
+                .about("Set a property of an interface")
+                .arg(
+                    Arg::with_name("bus-name")
+                        .required(true)
+                        .help("Name of the bus"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .help("Path of the object"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .required(true)
+                        .help("Interface name"),
+                )
+                .arg(
+                    Arg::with_name("property")
+                        .required(true)
+                        .help("Property name"),
+                )
+                .arg(
+                    Arg::with_name("value")
+                        .required_unless_one(&["json-args", "json-args-file"])
+                        .help("Value of the declared property type"),
+                ),
+                &["value"],
+                "Value of the declared property type as JSON",
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("get-all")
+                .about("Get all properties of an interface")
+                .arg(
+                    Arg::with_name("bus-name")
+                        .required(true)
+                        .help("Name of the bus"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .help("Path of the object"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .required(true)
+                        .help("Interface name"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("shell")
+                .about("Explore services interactively with cd, ls and tab completion"),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Print a completion script that completes bus names, paths and members live")
+                .arg(
+                    Arg::with_name("shell")
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "fish"])
+                        .help("Shell to complete in"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("monitor")
+                .about("Monitor messages on the bus")
+                .alias("m")
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["signal", "method_call", "method_return", "error"])
+                        .help("Only show messages of this type"),
+                )
+                .arg(
+                    Arg::with_name("sender")
+                        .long("sender")
+                        .takes_value(true)
+                        .help("Only show messages sent by this bus name"),
+                )
+                .arg(
+                    Arg::with_name("interface")
+                        .long("interface")
+                        .takes_value(true)
+                        .help("Only show messages for this interface"),
+                )
+                .arg(
+                    Arg::with_name("member")
+                        .long("member")
+                        .takes_value(true)
+                        .help("Only show messages for this method or signal name"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .takes_value(true)
+                        .help("Only show messages for this object path"),
                 ),
         )
         .arg(
@@ -89,11 +352,101 @@ fn main() {
                 .short("d")
                 .value_name("ADDRESS")
                 .default_value("session")
-                .help("A custom dbus address")
+                .help("session, system, starter or a dbus address like unix:path=/run/dbus/system_bus_socket. Multiple addresses separated by ';' are tried in order")
                 .env("DBUS_CLIENT_ADDRESS"),
-        );
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FORMAT")
+                .possible_values(&["text", "json", "json-pretty"])
+                .default_value("text")
+                .help("Output format"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .short("t")
+                .long("timeout")
+                .value_name("SECONDS")
+                .default_value("25")
+                .validator(|timeout| parse_timeout(&timeout).map(|_| ()))
+                .help("Seconds to wait for a reply or infinite"),
+        )
+        .arg(
+            Arg::with_name("no-reply")
+                .long("no-reply")
+                .alias("async")
+                .help("Do not expect a reply to method calls, the call returns as soon as it is sent"),
+        )
+        .arg(
+            Arg::with_name("no-auto-start")
+                .long("no-auto-start")
+                .help("Do not start the destination service if it is not running"),
+        )
+        .arg(
+            Arg::with_name("allow-interactive-auth")
+                .long("allow-interactive-auth")
+                .help(
+                    "Allow the service to ask the user for authorization, e.g. through polkit, \
+                     not supported with file descriptor arguments",
+                ),
+        )
+}
+
+/// Adds `--json-args` and `--json-args-file` to `cmd`, they conflict with the literal arguments
+/// named in `conflicts`.
+#[cfg(feature = "serde")]
+fn with_json_args<'a>(cmd: App<'a, 'a>, conflicts: &'a [&'a str], help: &'a str) -> App<'a, 'a> {
+    cmd.arg(
+        Arg::with_name("json-args")
+            .long("json-args")
+            .value_name("JSON")
+            .takes_value(true)
+            .conflicts_with_all(conflicts)
+            .help(help),
+    )
+    .arg(
+        Arg::with_name("json-args-file")
+            .long("json-args-file")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with("json-args")
+            .conflicts_with_all(conflicts)
+            .help("Like --json-args but read from FILE, - for stdin"),
+    )
+}
+
+/// Without the `serde` feature there are no JSON arguments.
+#[cfg(not(feature = "serde"))]
+fn with_json_args<'a>(cmd: App<'a, 'a>, _: &'a [&'a str], _: &'a str) -> App<'a, 'a> {
+    cmd
+}
 
-    let matches = app.get_matches();
+fn main() {
+    let matches = app().subcommand(completion::subcommand()).get_matches();
+
+    match matches.subcommand() {
+        ("completions", Some(cmd)) => {
+            return completion::print_script(cmd.value_of("shell").unwrap());
+        }
+        ("__complete", Some(cmd)) => {
+            let words = cmd
+                .values_of("words")
+                .map(|words| words.collect_vec())
+                .unwrap_or_default();
+
+            for candidate in completion::complete(
+                cmd.value_of("index").unwrap().parse().unwrap_or_default(),
+                &words,
+            ) {
+                println!("{}", candidate);
+            }
+
+            return;
+        }
+        _ => {}
+    }
 
     match matches.occurrences_of("v") {
         0 => SimpleLogger::new()
@@ -108,320 +461,1032 @@ fn main() {
             .with_level(LevelFilter::Info)
             .init()
             .unwrap(),
-        3 | _ => SimpleLogger::new()
+        _ => SimpleLogger::new()
             .with_level(LevelFilter::Debug)
             .init()
             .unwrap(),
     }
 
-    let connection = build_connection(matches.value_of("address").unwrap_or_default());
+    let output = match matches.value_of("output") {
+        Some("json") => Output::Json,
+        Some("json-pretty") => Output::JsonPretty,
+        _ => Output::Text,
+    };
+
+    let options = Options {
+        // The validator has rejected every timeout parse_timeout fails on.
+        timeout: matches
+            .value_of("timeout")
+            .and_then(|timeout| parse_timeout(timeout).ok())
+            .unwrap_or(TIMEOUT_INFINITE),
+        no_auto_start: matches.is_present("no-auto-start"),
+        allow_interactive_auth: matches.is_present("allow-interactive-auth"),
+    };
+
+    if let Err(err) = run(&matches, options, output) {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
+    }
+}
+
+/// The `--timeout` in seconds or `infinite`, libdbus takes at most [`TIMEOUT_INFINITE`].
+fn parse_timeout(timeout: &str) -> Result<Duration, String> {
+    if timeout == "infinite" {
+        return Ok(TIMEOUT_INFINITE);
+    }
+
+    timeout
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .filter(|duration| *duration <= TIMEOUT_INFINITE)
+        .ok_or_else(|| {
+            format!(
+                "Invalid timeout: {}, expected at most {} seconds or infinite",
+                timeout,
+                TIMEOUT_INFINITE.as_secs()
+            )
+        })
+}
+
+fn run(matches: &ArgMatches, options: Options, output: Output) -> Result<(), DBusError> {
+    let client = Client::connect(matches.value_of("address").unwrap_or_default(), options)?;
+    let no_reply = matches.is_present("no-reply");
 
     match matches.subcommand() {
-        ("list-names", Some(_cmd)) => {
-            list_names(connection);
-        }
-        ("introspect", Some(cmd)) => {
-            introspect(
-                &connection,
-                &cmd.value_of("bus-name").unwrap().into(),
-                &cmd.value_of("path").unwrap().into(),
-            );
+        ("list-names", Some(_cmd)) => list_names(&client, output),
+        ("introspect", Some(cmd)) => introspect(
+            &client,
+            output,
+            cmd.value_of("bus-name").unwrap(),
+            cmd.value_of("path").unwrap(),
+        ),
+        ("tree", Some(cmd)) => tree(
+            &client,
+            output,
+            cmd.value_of("bus-name").unwrap(),
+            cmd.value_of("path").unwrap(),
+            cmd.value_of("max-depth")
+                .map(|depth| depth.parse().unwrap()),
+            cmd.value_of("interface"),
+        ),
+        ("call", Some(cmd)) => {
+            let bus_name = cmd.value_of("bus-name").unwrap();
+            let path = cmd.value_of("path").unwrap();
+            let interface_name = cmd.value_of("interface").unwrap();
+            let method_name = cmd.value_of("method").unwrap();
+            let method = match cmd.value_of("signature") {
+                Some(signature) => Method::from_signature(method_name, signature)?,
+                None => client.find_method(bus_name, path, interface_name, method_name)?,
+            };
+
+            call(
+                &client,
+                output,
+                new_method_call(bus_name, path, interface_name, method_name)?,
+                &method,
+                cmd.values_of("argument")
+                    .map(|values| values.collect_vec())
+                    .unwrap_or_default(),
+                cmd.values_of("arg")
+                    .map(|values| values.collect_vec())
+                    .unwrap_or_default(),
+                json_args(cmd)?.as_deref(),
+                no_reply || method.is_no_reply(),
+                cmd.value_of("fd-output"),
+            )
         }
-        ("call", Some(cmd)) => call(
-            &connection,
-            &cmd.value_of("bus-name").unwrap().into(),
-            &cmd.value_of("path").unwrap().into(),
-            cmd.value_of("interface").unwrap().into(),
-            cmd.value_of("method").unwrap().into(),
+        ("call-raw", Some(cmd)) => call_raw(
+            &client,
+            output,
+            new_method_call(
+                cmd.value_of("bus-name").unwrap(),
+                cmd.value_of("path").unwrap(),
+                cmd.value_of("interface").unwrap(),
+                cmd.value_of("method").unwrap(),
+            )?,
+            cmd.value_of("signature").unwrap_or(""),
             cmd.value_of("argument").unwrap_or(""),
+            no_reply,
+            cmd.value_of("fd-output"),
+        ),
+        ("emit", Some(cmd)) => emit(
+            &client,
+            cmd.value_of("path").unwrap(),
+            cmd.value_of("interface").unwrap(),
+            cmd.value_of("member").unwrap(),
+            cmd.value_of("destination"),
+            cmd.value_of("signature").unwrap_or(""),
+            cmd.value_of("argument").unwrap_or(""),
+            json_args(cmd)?.as_deref(),
+        ),
+        ("get", Some(cmd)) => print_reply(
+            output,
+            &client.get_property(
+                cmd.value_of("bus-name").unwrap(),
+                cmd.value_of("path").unwrap(),
+                cmd.value_of("interface").unwrap(),
+                cmd.value_of("property").unwrap(),
+            )?,
+        ),
+        ("set", Some(cmd)) => set_property(
+            &client,
+            cmd.value_of("bus-name").unwrap(),
+            cmd.value_of("path").unwrap(),
+            cmd.value_of("interface").unwrap(),
+            cmd.value_of("property").unwrap(),
+            cmd.value_of("value"),
+            json_args(cmd)?.as_deref(),
+        ),
+        ("get-all", Some(cmd)) => print_reply(
+            output,
+            &client.get_all_properties(
+                cmd.value_of("bus-name").unwrap(),
+                cmd.value_of("path").unwrap(),
+                cmd.value_of("interface").unwrap(),
+            )?,
+        ),
+        ("shell", Some(_cmd)) => shell::run(&client, output, no_reply),
+        ("monitor", Some(cmd)) => client.monitor(
+            build_match_rules(
+                cmd.values_of("type")
+                    .map(|types| types.collect_vec())
+                    .unwrap_or_default(),
+                cmd.value_of("sender"),
+                cmd.value_of("interface"),
+                cmd.value_of("member"),
+                cmd.value_of("path"),
+            )?,
+            |message| {
+                // A message that can not be decoded does not end the monitor.
+                if let Err(err) = print_message(message, output) {
+                    eprintln!("{}", err);
+                }
+            },
         ),
         _ => {
-            println!("{}", matches.usage())
+            println!("{}", matches.usage());
+            Ok(())
         }
     }
 }
 
-#[derive(Debug)]
-enum Entry {
-    Node { name: String },
-    Interface { name: String, methods: Vec<Method> },
-    Signal { name: String },
+#[allow(clippy::too_many_arguments)]
+fn call(
+    client: &Client,
+    output: Output,
+    message: Message,
+    method: &Method,
+    values: Vec<&str>,
+    named_values: Vec<&str>,
+    json: Option<&str>,
+    no_reply: bool,
+    fd_output: Option<&str>,
+) -> Result<(), DBusError> {
+    let (dbus_type, dbus_value) = match json {
+        Some(json) => json_arguments(
+            method
+                .inputs()
+                .map(|input| DBusType::try_from(input.typ.as_str()))
+                .collect::<Result<Vec<DBusType>, DBusError>>()?,
+            json,
+        )?,
+        None => method_arguments(method, values, named_values)?,
+    };
+
+    do_call(
+        client,
+        output,
+        message,
+        DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        },
+        no_reply,
+        fd_output,
+    )
 }
 
-#[derive(Debug)]
-struct Method {
-    name: String,
-    args: Vec<Argument>,
+#[allow(clippy::too_many_arguments)]
+fn call_raw(
+    client: &Client,
+    output: Output,
+    message: Message,
+    signature: &str,
+    args: &str,
+    no_reply: bool,
+    fd_output: Option<&str>,
+) -> Result<(), DBusError> {
+    let dbus_type = arguments_type(signature)?;
+    let dbus_value = DBusValue::parse(args, &dbus_type)?;
+
+    do_call(
+        client,
+        output,
+        message,
+        DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        },
+        no_reply,
+        fd_output,
+    )
 }
 
-#[derive(Debug)]
-struct Argument {
-    name: String,
-    typ: String,
-    direction: Option<String>,
+fn do_call(
+    client: &Client,
+    output: Output,
+    message: Message,
+    args: DBusArgument,
+    no_reply: bool,
+    fd_output: Option<&str>,
+) -> Result<(), DBusError> {
+    if no_reply {
+        return client.call_no_reply(message, args);
+    }
+
+    let items = client.call(message, args)?;
+
+    print_reply(output, &items)?;
+
+    receive_fds(&items, fd_output)
 }
 
-fn call(
-    connection: &Connection,
-    bus_name: &String,
-    path: &String,
-    interface_name: String,
-    method_name: String,
+#[allow(clippy::too_many_arguments)]
+fn emit(
+    client: &Client,
+    path: &str,
+    interface_name: &str,
+    signal_name: &str,
+    destination: Option<&str>,
+    signature: &str,
     args: &str,
-) {
-    let entries = describe(bus_name, path, connection);
+    json: Option<&str>,
+) -> Result<(), DBusError> {
+    let (dbus_type, dbus_value) = match json {
+        Some(json) => json_arguments(DBusType::parse(signature)?, json)?,
+        None => {
+            let dbus_type = arguments_type(signature)?;
+            let dbus_value = DBusValue::parse(args, &dbus_type)?;
 
-    let interface = entries.iter().find(|entry| {
-        if let Entry::Interface { name, methods: _ } = entry {
-            name.eq(&interface_name)
-        } else {
-            false
+            (dbus_type, dbus_value)
         }
-    });
+    };
 
-    debug!("Found interface: {:?}\n", interface);
+    client.emit(
+        path,
+        interface_name,
+        signal_name,
+        destination,
+        DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        },
+    )
+}
+
+fn set_property(
+    client: &Client,
+    bus_name: &str,
+    path: &str,
+    interface_name: &str,
+    property_name: &str,
+    value: Option<&str>,
+    json: Option<&str>,
+) -> Result<(), DBusError> {
+    let property = client.find_property(bus_name, path, interface_name, property_name)?;
+    let (dbus_type, dbus_value) = property_value(&property, value, json)?;
+
+    client.set_property(
+        bus_name,
+        path,
+        interface_name,
+        property_name,
+        DBusArgument {
+            dbus_type: &dbus_type,
+            dbus_value: &dbus_value,
+        },
+    )
+}
+
+/// The new value of a writable `property`, given as a literal or as JSON.
+fn property_value(
+    property: &Property,
+    value: Option<&str>,
+    json: Option<&str>,
+) -> Result<(DBusType, DBusValue), DBusError> {
+    if property.access == "read" {
+        return Err(DBusError::Unsupported(format!(
+            "Property: {} is read only",
+            property.name
+        )));
+    }
+
+    let dbus_type = DBusType::try_from(property.typ.as_str())?;
+    let dbus_value = match json {
+        Some(json) => from_json(json, &dbus_type)?,
+        None => DBusValue::parse(value.unwrap_or_default(), &dbus_type)?,
+    };
 
-    if let Some(interface) = interface {
-        if let Entry::Interface { name: _, methods } = interface {
-            let method = methods
+    Ok((dbus_type, dbus_value))
+}
+
+fn list_names(client: &Client, output: Output) -> Result<(), DBusError> {
+    let names = client.list_names()?;
+
+    if let Output::Text = output {
+        println!("bus names:\n");
+
+        names.iter().for_each(|name| println!("    {}", name));
+    } else {
+        output.print_json(&json!(names));
+    }
+
+    Ok(())
+}
+
+fn introspect(
+    client: &Client,
+    output: Output,
+    bus_name: &str,
+    path: &str,
+) -> Result<(), DBusError> {
+    let entries = client.introspect(bus_name, path)?;
+
+    if let Output::Text = output {
+        print_entries(&entries);
+    } else {
+        output.print_json(&json!({
+            "nodes": entries
                 .iter()
-                .find(|method| method.name.as_str() == method_name.as_str());
-
-            if let Some(method) = method {
-                debug!("Found method: {:?}\n", method);
-
-                let signature = method
-                    .args
-                    .iter()
-                    .filter(|arg| arg.direction.eq(&Some("in".into())))
-                    .map(|arg| arg.typ.clone())
-                    .join("");
-
-                debug!("Signature: {:?}\n", Into::<String>::into(&signature));
-
-                let dbus_type: DBusType = if signature.is_empty() { 
-                    signature.as_str().into()
-                } else {
-                    format!("({})", signature).as_str().into()
-                };
-                
-                let dbus_value: DBusValue = args.into();
-
-                do_call(
-                    connection,
-                    bus_name,
-                    path,
-                    interface_name,
-                    method_name,
-                    DBusArgument {
-                        dbus_type: &dbus_type,
-                        dbus_value: &dbus_value,
-                    },
-                );
-            } else {
-                println!("No method: {} found!", method_name)
-            }
+                .filter_map(|entry| match entry {
+                    Entry::Node { name } => Some(name),
+                    _ => None,
+                })
+                .collect_vec(),
+            "interfaces": entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    Entry::Interface {
+                        name,
+                        methods,
+                        signals,
+                        properties,
+                        annotations,
+                    } => Some(json!({
+                        "name": name,
+                        "annotations": annotations_json(annotations),
+                        "methods": methods.iter().map(Into::<Value>::into).collect_vec(),
+                        "signals": signals.iter().map(Into::<Value>::into).collect_vec(),
+                        "properties": properties.iter().map(Into::<Value>::into).collect_vec(),
+                    })),
+                    _ => None,
+                })
+                .collect_vec(),
+        }));
+    }
+
+    Ok(())
+}
+
+fn tree(
+    client: &Client,
+    output: Output,
+    bus_name: &str,
+    path: &str,
+    max_depth: Option<usize>,
+    interface_name: Option<&str>,
+) -> Result<(), DBusError> {
+    let mut root = client.tree(bus_name, path, max_depth)?;
+
+    if let Some(interface_name) = interface_name {
+        if !root.filter(interface_name) {
+            return Err(DBusError::NotFound(format!(
+                "No object implements interface: {} below {}",
+                interface_name, path
+            )));
         }
     }
+
+    if let Output::Text = output {
+        print_object(&root, 0);
+    } else {
+        output.print_json(&(&root).into());
+    }
+
+    Ok(())
 }
 
-fn do_call(
-    connection: &Connection,
-    bus_name: &String,
-    path: &String,
-    interface_name: String,
-    method_name: String,
-    args: DBusArgument,
-) {
-    let mut message = Message::call_with_args(bus_name, path, interface_name, method_name, ());
-
-    match args.validate() {
-        Ok(args) => {
-            if let DBusType::Struct(vec) = args.dbus_type {
-                if let Some(message_item) = args.into() {
-                    if let MessageItem::Struct(vec) = message_item {
-                        vec.into_iter().for_each(|arg| {
-                            message.append_items(&[arg]);
-                        });
-                    } else {
-                        panic!("Top level argument has to be a struct")
-                    }
-                }
+#[derive(Debug, Clone, Copy)]
+enum Output {
+    Text,
+    Json,
+    JsonPretty,
+}
+
+impl Output {
+    fn print_json(&self, value: &Value) {
+        if let Output::JsonPretty = self {
+            println!("{:#}", value)
+        } else {
+            println!("{}", value)
+        }
+    }
+}
+
+/// Assigns the `name=value` pairs to the in-arguments of the same name and the positional values
+/// to the remaining in-arguments in order.
+fn method_arguments(
+    method: &Method,
+    values: Vec<&str>,
+    named_values: Vec<&str>,
+) -> Result<(DBusType, DBusValue), DBusError> {
+    let inputs = method.inputs().collect_vec();
+    let mut slots: Vec<Option<&str>> = vec![None; inputs.len()];
+
+    for named_value in named_values {
+        let (name, value) = named_value.split_once('=').ok_or_else(|| {
+            DBusError::Usage(format!(
+                "Expected name=value got: {}\n{}",
+                named_value,
+                method.usage()
+            ))
+        })?;
+
+        let index = inputs
+            .iter()
+            .position(|input| !input.name.is_empty() && input.name == name)
+            .ok_or_else(|| {
+                DBusError::Usage(format!("No argument: {}\n{}", name, method.usage()))
+            })?;
+
+        if slots[index].replace(value).is_some() {
+            return Err(DBusError::Usage(format!(
+                "Argument: {} is given more than once\n{}",
+                name,
+                method.usage()
+            )));
+        }
+    }
+
+    let mut values = values.into_iter();
+
+    slots
+        .iter_mut()
+        .filter(|slot| slot.is_none())
+        .for_each(|slot| *slot = values.next());
+
+    if values.next().is_some() || slots.iter().any(Option::is_none) {
+        return Err(DBusError::Usage(format!(
+            "{} expects {} argument(s)\n{}",
+            method.name,
+            inputs.len(),
+            method.usage()
+        )));
+    }
+
+    if inputs.is_empty() {
+        return Ok((DBusType::Unit, DBusValue::Unit));
+    }
+
+    let types = inputs
+        .iter()
+        .map(|input| DBusType::try_from(input.typ.as_str()))
+        .collect::<Result<Vec<DBusType>, DBusError>>()?;
+    let values = types
+        .iter()
+        .zip(slots.into_iter().flatten())
+        .map(|(dbus_type, value)| DBusValue::parse(value, dbus_type))
+        .collect::<Result<Vec<DBusValue>, DBusError>>()?;
+
+    Ok((DBusType::Struct(types), DBusValue::Vec(values)))
+}
+
+/// The JSON of `--json-args` or the contents of the `--json-args-file`, `-` is stdin.
+fn json_args(cmd: &ArgMatches) -> Result<Option<String>, DBusError> {
+    match (cmd.value_of("json-args"), cmd.value_of("json-args-file")) {
+        (Some(json), _) => Ok(Some(json.to_string())),
+        (None, Some("-")) => io::read_to_string(io::stdin())
+            .map(Some)
+            .map_err(|err| DBusError::Io(format!("Could not read stdin: {}", err))),
+        (None, Some(path)) => fs::read_to_string(path)
+            .map(Some)
+            .map_err(|err| DBusError::Io(format!("Could not read {}: {}", path, err))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Converts `json` into a value of `dbus_type`, the command line may name files to pass.
+#[cfg(feature = "serde")]
+fn from_json(json: &str, dbus_type: &DBusType) -> Result<DBusValue, DBusError> {
+    dbus_client::dbus_serde::from_json_with_files(json, dbus_type)
+}
+
+/// Without the `serde` feature the command line has no JSON arguments to convert.
+#[cfg(not(feature = "serde"))]
+fn from_json(_: &str, _: &DBusType) -> Result<DBusValue, DBusError> {
+    Err(DBusError::Unsupported(
+        "JSON arguments need the serde feature".into(),
+    ))
+}
+
+/// Converts a JSON array with one element per type in `types` into the arguments of a message.
+fn json_arguments(types: Vec<DBusType>, json: &str) -> Result<(DBusType, DBusValue), DBusError> {
+    let dbus_type = DBusType::Struct(types);
+    let dbus_value = from_json(json, &dbus_type)?;
+
+    match dbus_type {
+        DBusType::Struct(types) if types.is_empty() => Ok((DBusType::Unit, DBusValue::Unit)),
+        dbus_type => Ok((dbus_type, dbus_value)),
+    }
+}
+
+/// Describes the file descriptors of a reply on stderr and copies their contents to
+/// `fd_output`, `-` is stdout.
+fn receive_fds(items: &[MessageItem], fd_output: Option<&str>) -> Result<(), DBusError> {
+    let fds = items
+        .iter()
+        .map(DBusValue::try_from)
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .flat_map(dbus_fd::collect)
+        .collect_vec();
+
+    fds.iter()
+        .for_each(|fd| eprintln!("{}", dbus_fd::describe(*fd)));
+
+    let mut out: Box<dyn Write> = match fd_output {
+        None => return Ok(()),
+        Some("-") => Box::new(io::stdout()),
+        Some(path) => Box::new(
+            File::create(path)
+                .map_err(|err| DBusError::Io(format!("Could not create {}: {}", path, err)))?,
+        ),
+    };
+
+    for fd in fds {
+        dbus_fd::copy(fd, &mut out)?;
+    }
+
+    out.flush()
+        .map_err(|err| DBusError::Io(format!("Could not write fd contents: {}", err)))
+}
+
+fn print_reply(output: Output, items: &[MessageItem]) -> Result<(), DBusError> {
+    let (types, values) = decode(items)?.into_iter().unzip();
+    let dbus_type = DBusType::Struct(types);
+    let dbus_value = DBusValue::Vec(values);
+    let reply = DBusArgument {
+        dbus_type: &dbus_type,
+        dbus_value: &dbus_value,
+    };
+
+    match output {
+        Output::Text => {
+            if !items.is_empty() {
+                println!("{:#}", reply)
             }
         }
-        Err(e) => panic!("Invalid argument: {:?}", e),
+        _ => output.print_json(&(&reply).into()),
     }
 
-    let response = connection
-        .channel()
-        .send_with_reply_and_block(message, Duration::from_secs(1));
+    Ok(())
+}
 
-    println!("{:?}", response.unwrap())
+/// The types and values of received message items.
+fn decode(items: &[MessageItem]) -> Result<Vec<(DBusType, DBusValue)>, DBusError> {
+    items
+        .iter()
+        .map(|item| {
+            Ok((
+                DBusType::try_from(&*item.signature())?,
+                DBusValue::try_from(item)?,
+            ))
+        })
+        .collect()
 }
 
-fn list_names(connection: Connection) {
-    let proxy = connection.with_proxy("org.freedesktop.DBus", "/", Duration::from_secs(1));
-    let (names,): (Vec<String>,) = proxy
-        .method_call("org.freedesktop.DBus", "ListNames", ())
-        .unwrap();
+fn build_match_rules(
+    types: Vec<&str>,
+    sender: Option<&str>,
+    interface: Option<&str>,
+    member: Option<&str>,
+    path: Option<&str>,
+) -> Result<Vec<MatchRule<'static>>, DBusError> {
+    let mut rule = MatchRule::new();
 
-    println!("bus names:\n");
+    rule.sender = sender
+        .map(|sender| BusName::new(sender.to_string()))
+        .transpose()
+        .map_err(DBusError::InvalidName)?;
+    rule.interface = interface
+        .map(|interface| Interface::new(interface.to_string()))
+        .transpose()
+        .map_err(DBusError::InvalidName)?;
+    rule.member = member
+        .map(|member| Member::new(member.to_string()))
+        .transpose()
+        .map_err(DBusError::InvalidName)?;
+    rule.path = path
+        .map(|path| Path::new(path.to_string()))
+        .transpose()
+        .map_err(DBusError::InvalidName)?;
 
-    names.iter().for_each(|name| println!("    {}", name));
+    if types.is_empty() {
+        Ok(vec![rule])
+    } else {
+        Ok(types
+            .into_iter()
+            .map(|typ| {
+                let mut rule = rule.clone();
+
+                rule.msg_type = Some(match typ {
+                    "signal" => MessageType::Signal,
+                    "method_call" => MessageType::MethodCall,
+                    "method_return" => MessageType::MethodReturn,
+                    _ => MessageType::Error,
+                });
+
+                rule
+            })
+            .collect_vec())
+    }
 }
 
-fn introspect(connection: &Connection, bus_name: &String, path: &String) {
-    // let (nodes, interfaces) = do_introspect(connection, bus_name, path);
+fn print_message(mut message: Message, output: Output) -> Result<(), DBusError> {
+    let kind = match message.msg_type() {
+        MessageType::Signal => "signal",
+        MessageType::MethodCall => "method_call",
+        MessageType::MethodReturn => "method_return",
+        MessageType::Error => "error",
+    };
+    let sender = message.sender().map(|sender| sender.to_string());
+    let destination = message
+        .destination()
+        .map(|destination| destination.to_string());
+    let serial = message.get_serial();
+    let reply_serial = message.get_reply_serial();
+    let path = message.path().map(|path| path.to_string());
+    let interface = message.interface().map(|interface| interface.to_string());
+    let member = message.member().map(|member| member.to_string());
+    let error_name = message
+        .as_result()
+        .err()
+        .and_then(|err| err.name().map(|name| name.to_string()));
+
+    let body = decode(&message.get_items())?;
+
+    if let Output::Text = output {
+        let mut header = vec![
+            kind.to_string(),
+            format!("sender={}", sender.unwrap_or_default()),
+            format!("-> destination={}", destination.unwrap_or_default()),
+            format!("serial={}", serial.unwrap_or_default()),
+        ];
 
-    let entries = describe(bus_name, path, connection);
+        if let Some(reply_serial) = reply_serial {
+            header.push(format!("reply_serial={}", reply_serial));
+        }
+
+        if let Some(path) = path {
+            header.push(format!("path={}", path));
+        }
+
+        if let Some(interface) = interface {
+            header.push(format!("interface={}", interface));
+        }
+
+        if let Some(member) = member {
+            header.push(format!("member={}", member));
+        }
 
+        if let Some(error_name) = error_name {
+            header.push(format!("error_name={}", error_name));
+        }
+
+        println!("{}", header.join(" "));
+
+        body.iter().for_each(|(dbus_type, dbus_value)| {
+            print(
+                1,
+                &format!(
+                    "{} {}",
+                    String::from(dbus_type),
+                    DBusArgument {
+                        dbus_type,
+                        dbus_value,
+                    }
+                ),
+            );
+        });
+    } else {
+        output.print_json(&json!({
+            "type": kind,
+            "sender": sender,
+            "destination": destination,
+            "serial": serial,
+            "reply_serial": reply_serial,
+            "path": path,
+            "interface": interface,
+            "member": member,
+            "error_name": error_name,
+            "signature": body.iter().map(|(dbus_type, _)| String::from(dbus_type)).join(""),
+            "body": body
+                .iter()
+                .map(|(dbus_type, dbus_value)| Value::from(&DBusArgument {
+                    dbus_type,
+                    dbus_value,
+                }))
+                .collect_vec(),
+        }));
+    }
+
+    Ok(())
+}
+
+fn print_object(object: &Object, depth: u32) {
+    match &object.error {
+        Some(err) => print(depth, &format!("{} ({})", object.path, err)),
+        None => print(depth, &object.path),
+    }
+
+    object
+        .interfaces
+        .iter()
+        .for_each(|interface| print(depth + 1, interface));
+    object
+        .children
+        .iter()
+        .for_each(|child| print_object(child, depth + 1));
+}
+
+fn print_entries(entries: &[Entry]) {
     println!("paths:\n");
 
-    entries.iter().for_each(|entry| match entry {
-        Entry::Node { name } => print(1, name),
-        _ => {}
+    entries.iter().for_each(|entry| {
+        if let Entry::Node { name } = entry {
+            print(1, name)
+        }
     });
 
     println!("\ninterfaces:\n");
 
-    entries.iter().for_each(|entry| match entry {
-        Entry::Interface { name, methods } => {
+    entries.iter().for_each(|entry| {
+        if let Entry::Interface {
+            name,
+            methods,
+            signals,
+            properties,
+            annotations,
+        } = entry
+        {
             print(1, name);
+            print_annotations(2, annotations);
             methods.iter().for_each(|method| {
                 print(
                     2,
                     &format!(
                         "{} ( {} ) -> {}",
                         &method.name,
-                        method
-                            .args
-                            .iter()
-                            .filter(|arg| arg.direction.eq(&Some("in".into())))
-                            .map(|arg| arg.typ.clone())
-                            .join(""),
-                        method
-                            .args
-                            .iter()
-                            .filter(|arg| arg.direction.eq(&Some("out".into())))
-                            .map(|arg| arg.typ.clone())
-                            .join("")
+                        method.inputs().map(|arg| arg.typ.clone()).join(""),
+                        method.outputs().map(|arg| arg.typ.clone()).join("")
                     ),
                 );
+                print_annotations(3, &method.annotations);
+                print_argument_annotations(3, &method.args);
+            });
+            signals.iter().for_each(|signal| {
+                print(
+                    2,
+                    &format!(
+                        "signal {} ( {} )",
+                        &signal.name,
+                        signal.args.iter().map(|arg| arg.typ.clone()).join("")
+                    ),
+                );
+                print_annotations(3, &signal.annotations);
+                print_argument_annotations(3, &signal.args);
+            });
+            properties.iter().for_each(|property| {
+                print(
+                    2,
+                    &format!(
+                        "{}: {} ({})",
+                        &property.name, &property.typ, &property.access
+                    ),
+                );
+                print_annotations(3, &property.annotations);
             })
         }
-        _ => {}
     });
 }
 
-fn build_connection(address: &str) -> Connection {
-    if address.eq("session") {
-        Connection::from(Channel::get_private(dbus::channel::BusType::Session).unwrap())
-    } else if address.eq("system") {
-        Connection::from(Channel::get_private(dbus::channel::BusType::Session).unwrap())
-    } else {
-        Connection::from(Channel::open_private(address).unwrap())
-    }
+fn print_annotations(indent: u32, annotations: &[Annotation]) {
+    annotations.iter().for_each(|annotation| {
+        print(
+            indent,
+            &format!("@{} = {}", annotation.name, annotation.value),
+        )
+    });
 }
 
-fn describe(bus_name: &String, object_path: &String, connection: &Connection) -> Vec<Entry> {
-    let proxy = connection.with_proxy(bus_name, object_path, Duration::from_secs(1));
+fn print_argument_annotations(indent: u32, args: &[Argument]) {
+    args.iter().enumerate().for_each(|(index, arg)| {
+        arg.annotations.iter().for_each(|annotation| {
+            let name = if arg.name.is_empty() {
+                format!("arg{}", index)
+            } else {
+                arg.name.clone()
+            };
 
-    let (capas,): (String,) = proxy
-        .method_call("org.freedesktop.DBus.Introspectable", "Introspect", ())
-        .unwrap();
+            print(
+                indent,
+                &format!("@{} = {} ({})", annotation.name, annotation.value, name),
+            )
+        })
+    });
+}
 
-    debug!("{:?}", capas);
+fn print(indent: u32, subject: &String) {
+    let ind = (0..indent).map(|_| "    ").collect::<Vec<&str>>().join("");
 
-    let mut entries = Vec::new();
-    let parser = EventReader::from_str(capas.as_str());
+    println!("{}{}", ind, subject);
+}
 
-    for e in parser {
-        match e {
-            Ok(elem) => {
-                debug!("{:?}", elem);
-                match elem {
-                    XmlEvent::StartElement {
-                        name,
-                        attributes,
-                        namespace: _,
-                    } => match name.local_name.as_str() {
-                        "node" => {
-                            if attributes.get("name").is_some() {
-                                entries.push(Entry::Node {
-                                    name: attributes.get("name").unwrap().value.clone(),
-                                })
-                            }
-                        }
-                        "interface" => entries.push(Entry::Interface {
-                            name: attributes.get("name").unwrap().value.clone(),
-                            methods: Vec::new(),
-                        }),
-                        "signal" => entries.push(Entry::Signal {
-                            name: attributes.get("name").unwrap().value.clone(),
-                        }),
-                        "method" => {
-                            if let Entry::Interface { name: _, methods } =
-                                entries.last_mut().unwrap()
-                            {
-                                (*methods).push(Method {
-                                    name: attributes.get("name").unwrap().value.clone(),
-                                    args: Vec::new(),
-                                });
-                            } else {
-                            }
-                        }
-                        "arg" => {
-                            if let Entry::Interface { name: _, methods } =
-                                entries.last_mut().unwrap()
-                            {
-                                let method = methods.last_mut().unwrap();
-
-                                method.args.push(Argument {
-                                    name: attributes
-                                        .get("name")
-                                        .map(|attribute| attribute.value.clone())
-                                        .unwrap_or("".into()),
-                                    typ: attributes.get("type").unwrap().value.clone(),
-                                    direction: attributes
-                                        .get("direction")
-                                        .map(|direction| direction.value.clone()),
-                                });
-                            } else {
-                            }
-                        }
-                        _ => {}
-                    },
-                    // XmlEvent::EndElement { name } => todo!(),
-                    _ => {}
-                }
-            }
-            Err(err) => warn!("Xml error: {:?}", err),
-        }
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use dbus_client::{
+        client::{new_signal, TIMEOUT_INFINITE},
+        introspection::{Argument, Method, Property},
+        DBusError, DBusType, DBusValue,
+    };
+
+    use crate::{build_match_rules, method_arguments, parse_timeout, property_value};
+
+    #[test]
+    fn test_method_arguments() {
+        let method = Method {
+            name: "Resize".into(),
+            args: ["width", "height"]
+                .iter()
+                .map(|name| Argument {
+                    name: name.to_string(),
+                    typ: "u".into(),
+                    direction: None,
+                    annotations: Vec::new(),
+                })
+                .collect(),
+            annotations: Vec::new(),
+        };
+
+        assert_eq!(
+            method_arguments(&method, vec!["2u"], vec!["width=1u"]),
+            Ok((
+                DBusType::Struct(vec![DBusType::UInt32, DBusType::UInt32]),
+                DBusValue::Vec(vec![DBusValue::UInt32(1), DBusValue::UInt32(2)])
+            ))
+        );
+        assert_eq!(
+            method_arguments(&method, vec!["1u"], vec![]),
+            Err(DBusError::Usage(
+                "Resize expects 2 argument(s)\nUsage: Resize <width: u> <height: u>".into()
+            ))
+        );
+        assert!(matches!(
+            method_arguments(&method, vec![], vec!["depth=1u"]),
+            Err(DBusError::Usage(_))
+        ));
     }
 
-    entries
-}
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_arguments() {
+        use crate::json_arguments;
 
-trait Gettable {
-    fn get(&self, name: &str) -> Option<&OwnedAttribute>;
-}
+        assert_eq!(
+            json_arguments(
+                vec![DBusType::String, DBusType::UInt32],
+                r#"["org.test", 8]"#
+            ),
+            Ok((
+                DBusType::Struct(vec![DBusType::String, DBusType::UInt32]),
+                DBusValue::Vec(vec![
+                    DBusValue::String("org.test".into()),
+                    DBusValue::UInt32(8)
+                ])
+            ))
+        );
+        assert_eq!(
+            json_arguments(vec![], "[]"),
+            Ok((DBusType::Unit, DBusValue::Unit))
+        );
+        assert!(matches!(
+            json_arguments(vec![DBusType::UInt32], "[-1]"),
+            Err(DBusError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_property_value() {
+        let mut property = Property {
+            name: "Volume".into(),
+            typ: "u".into(),
+            access: "read".into(),
+            annotations: Vec::new(),
+        };
+
+        assert_eq!(
+            property_value(&property, Some("8"), None),
+            Err(DBusError::Unsupported(
+                "Property: Volume is read only".into()
+            ))
+        );
 
-impl Gettable for Vec<OwnedAttribute> {
-    fn get(&self, name: &str) -> Option<&OwnedAttribute> {
-        find_attribute(self, &name.into())
+        property.access = "readwrite".into();
+
+        assert_eq!(
+            property_value(&property, Some("8"), None),
+            Ok((DBusType::UInt32, DBusValue::UInt32(8)))
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            property_value(&property, None, Some("9")),
+            Ok((DBusType::UInt32, DBusValue::UInt32(9)))
+        );
+
+        property.access = "write".into();
+
+        assert!(matches!(
+            property_value(&property, Some("-1"), None),
+            Err(DBusError::InvalidValue { .. })
+        ));
     }
-}
 
-fn find_attribute<'l>(attrs: &'l Vec<OwnedAttribute>, name: &String) -> Option<&'l OwnedAttribute> {
-    attrs.iter().find(|attr| attr.name.local_name.eq(name))
-}
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_timeout("infinite"), Ok(TIMEOUT_INFINITE));
+        assert!(parse_timeout("2147483").is_ok());
+        assert!(parse_timeout("3000000").is_err());
+        assert!(parse_timeout("1e300").is_err());
+        assert!(parse_timeout("-1").is_err());
+        assert!(parse_timeout("NaN").is_err());
+    }
 
-fn print(indent: u32, subject: &String) {
-    let ind = (0..indent)
-        .into_iter()
-        .map(|_| "    ")
-        .collect::<Vec<&str>>()
-        .join("");
+    #[test]
+    fn test_build_match_rules() {
+        let signal = new_signal("/org/test", "org.test.Signals", "Changed", None).unwrap();
 
-    println!("{}{}", ind, subject);
+        let rules = build_match_rules(
+            vec!["method_call", "signal"],
+            None,
+            Some("org.test.Signals"),
+            Some("Changed"),
+            Some("/org/test"),
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert!(!rules[0].matches(&signal));
+        assert!(rules[1].matches(&signal));
+        assert!(
+            build_match_rules(vec![], None, None, Some("Other"), None).unwrap()[0]
+                .matches(&new_signal("/", "org.test.Signals", "Other", None).unwrap())
+        );
+        assert!(
+            !build_match_rules(vec![], None, None, Some("Other"), None).unwrap()[0]
+                .matches(&signal)
+        );
+        assert!(matches!(
+            build_match_rules(vec![], None, Some("not an interface"), None, None),
+            Err(DBusError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn test_signature_arguments() {
+        let method = Method::from_signature("Add", "ua{sv}").unwrap();
+
+        assert_eq!(
+            method_arguments(&method, vec!["1", "{}"], vec![]),
+            Ok((
+                DBusType::try_from("(ua{sv})").unwrap(),
+                DBusValue::Vec(vec![DBusValue::UInt32(1), DBusValue::Vec(vec![])])
+            ))
+        );
+        assert!(matches!(
+            method_arguments(&method, vec!["1"], vec![]),
+            Err(DBusError::Usage(_))
+        ));
+        assert_eq!(
+            method_arguments(&Method::from_signature("Ping", "").unwrap(), vec![], vec![]),
+            Ok((DBusType::Unit, DBusValue::Unit))
+        );
+        assert!(matches!(
+            Method::from_signature("Add", "u("),
+            Err(DBusError::InvalidSignature { .. })
+        ));
+    }
 }
@@ -0,0 +1,465 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::PathBuf,
+};
+
+use dbus_client::{
+    client::new_method_call, dbus_value::validate_object_path, introspection::Entry, Client,
+    DBusError,
+};
+use itertools::Itertools;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Context, Editor, Helper,
+};
+
+use crate::{
+    call, completion::bus_names, introspect, list_names, print_reply, set_property, Output,
+};
+
+const COMMANDS: &[&str] = &[
+    "cd", "ls", "pwd", "call", "get", "set", "get-all", "help", "exit",
+];
+
+const HELP: &str = "\
+cd [BUS_NAME | PATH]           Select a bus name or an object path, relative or absolute
+ls [PATH]                      List bus names or the children and interfaces of an object
+pwd                            Print the selected bus name and object path
+call INTERFACE METHOD [ARG]... Call a method on the selected object
+get INTERFACE PROPERTY         Get a property of the selected object
+set INTERFACE PROPERTY VALUE   Set a property of the selected object
+get-all INTERFACE              Get all properties of the selected object
+exit                           Leave the shell, as does Ctrl-D";
+
+/// Reads commands until `exit` or end of input, completing names with TAB.
+///
+/// History is kept in `$XDG_STATE_HOME/dbus-client/history`. Method calls do not wait for a reply
+/// if `no_reply` is set, the other global options are those of `client`.
+pub fn run(client: &Client, output: Output, no_reply: bool) -> Result<(), DBusError> {
+    let mut editor = Editor::new().map_err(readline_error)?;
+    let history = history_path();
+
+    editor.set_helper(Some(ShellHelper {
+        client,
+        location: Location::default(),
+        entries: RefCell::new(HashMap::new()),
+    }));
+
+    if let Some(history) = &history {
+        // There is no history on the first start.
+        let _ = editor.load_history(history);
+    }
+
+    loop {
+        let prompt = format!("{}> ", editor.helper().unwrap().location);
+
+        match editor.readline(&prompt) {
+            Ok(line) => {
+                editor.add_history_entry(line.as_str());
+
+                let helper = editor.helper_mut().unwrap();
+
+                helper.entries.get_mut().clear();
+
+                match execute(client, output, no_reply, &mut helper.location, &line) {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            Err(ReadlineError::Interrupted) => {}
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(readline_error(err)),
+        }
+    }
+
+    match history {
+        Some(history) => {
+            if let Some(dir) = history.parent() {
+                fs::create_dir_all(dir).map_err(|err| {
+                    DBusError::Io(format!("Could not create {}: {}", dir.display(), err))
+                })?;
+            }
+
+            editor.save_history(&history).map_err(readline_error)
+        }
+        None => Ok(()),
+    }
+}
+
+fn readline_error(err: ReadlineError) -> DBusError {
+    DBusError::Io(format!("Could not read the command line: {}", err))
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("dbus-client").join("history"))
+}
+
+/// Runs one line, returns `false` if the shell should exit.
+fn execute(
+    client: &Client,
+    output: Output,
+    no_reply: bool,
+    location: &mut Location,
+    line: &str,
+) -> Result<bool, DBusError> {
+    let words = split_words(line)
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect_vec();
+
+    match words.as_slice() {
+        [] => {}
+        ["exit"] | ["quit"] => return Ok(false),
+        ["help"] => println!("{}", HELP),
+        ["pwd"] => println!("{}", location),
+        ["cd"] => *location = Location::default(),
+        ["cd", target] => *location = location.cd(target)?,
+        ["ls"] => ls(client, output, location)?,
+        ["ls", target] => ls(client, output, &location.cd(target)?)?,
+        ["call", interface_name, method_name, values @ ..] => {
+            let bus_name = location.bus_name()?;
+            let method =
+                client.find_method(bus_name, &location.path, interface_name, method_name)?;
+
+            call(
+                client,
+                output,
+                new_method_call(bus_name, &location.path, interface_name, method_name)?,
+                &method,
+                values.to_vec(),
+                Vec::new(),
+                None,
+                no_reply || method.is_no_reply(),
+                None,
+            )?
+        }
+        ["get", interface_name, property_name] => print_reply(
+            output,
+            &client.get_property(
+                location.bus_name()?,
+                &location.path,
+                interface_name,
+                property_name,
+            )?,
+        )?,
+        ["set", interface_name, property_name, value] => set_property(
+            client,
+            location.bus_name()?,
+            &location.path,
+            interface_name,
+            property_name,
+            Some(value),
+            None,
+        )?,
+        ["get-all", interface_name] => print_reply(
+            output,
+            &client.get_all_properties(location.bus_name()?, &location.path, interface_name)?,
+        )?,
+        _ => {
+            return Err(DBusError::Usage(format!(
+                "Unknown command: {}\n{}",
+                line.trim(),
+                HELP
+            )))
+        }
+    }
+
+    Ok(true)
+}
+
+fn ls(client: &Client, output: Output, location: &Location) -> Result<(), DBusError> {
+    match &location.bus_name {
+        Some(bus_name) => introspect(client, output, bus_name, &location.path),
+        None => list_names(client, output),
+    }
+}
+
+/// Splits a line at whitespace outside of quotes and brackets, so value literals like
+/// `(hello, 8i)` stay one word. Returns the words with their byte offsets.
+fn split_words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        if c.is_whitespace() && depth == 0 && quote.is_none() {
+            if let Some(start) = start.take() {
+                words.push((start, &line[start..index]));
+            }
+            continue;
+        }
+
+        start.get_or_insert(index);
+
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{' | '<') => depth += 1,
+            (None, ')' | ']' | '}' | '>') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    if let Some(start) = start {
+        words.push((start, &line[start..]));
+    }
+
+    words
+}
+
+/// Bus names contain a dot or start with a colon but no slash, path elements never do.
+fn is_bus_name(word: &str) -> bool {
+    !word.contains('/') && (word.starts_with(':') || (word.contains('.') && !word.starts_with('.')))
+}
+
+/// The bus name and object path commands apply to, like a working directory.
+#[derive(Debug, PartialEq)]
+struct Location {
+    bus_name: Option<String>,
+    path: String,
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Location {
+            bus_name: None,
+            path: "/".into(),
+        }
+    }
+}
+
+impl Location {
+    fn bus_name(&self) -> Result<&str, DBusError> {
+        self.bus_name
+            .as_deref()
+            .ok_or_else(|| DBusError::Usage("No bus name selected, cd into one first".into()))
+    }
+
+    /// The location of `target`, a bus name or a path relative to this one.
+    fn cd(&self, target: &str) -> Result<Location, DBusError> {
+        if is_bus_name(target) {
+            return Ok(Location {
+                bus_name: Some(target.to_string()),
+                path: "/".into(),
+            });
+        }
+
+        let path = resolve(&self.path, target);
+
+        validate_object_path(&path).map_err(DBusError::InvalidName)?;
+
+        Ok(Location {
+            bus_name: Some(self.bus_name()?.to_string()),
+            path,
+        })
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.bus_name {
+            Some(bus_name) => write!(f, "{}:{}", bus_name, self.path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolves `target` against `path`, understanding `.` and `..`.
+fn resolve(path: &str, target: &str) -> String {
+    let mut elements = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        path.split('/')
+            .filter(|element| !element.is_empty())
+            .collect_vec()
+    };
+
+    for element in target.split('/') {
+        match element {
+            "" | "." => {}
+            ".." => {
+                elements.pop();
+            }
+            element => elements.push(element),
+        }
+    }
+
+    format!("/{}", elements.join("/"))
+}
+
+struct ShellHelper<'a> {
+    client: &'a Client,
+    location: Location,
+    /// Introspection results by bus name and path, cleared after every command.
+    entries: RefCell<HashMap<(String, String), Vec<Entry>>>,
+}
+
+impl ShellHelper<'_> {
+    fn candidates(&self, words: &[&str], word: &str) -> Vec<String> {
+        match words {
+            [] => COMMANDS.iter().map(|command| command.to_string()).collect(),
+            ["cd" | "ls"] => self.paths(word),
+            ["call" | "get" | "set" | "get-all"] => {
+                self.with_entries(&self.location.path, |entry| match entry {
+                    Entry::Interface { name, .. } => vec![name.clone()],
+                    _ => Vec::new(),
+                })
+            }
+            [command @ ("call" | "get" | "set"), interface_name] => {
+                self.with_entries(&self.location.path, |entry| match entry {
+                    Entry::Interface {
+                        name,
+                        methods,
+                        properties,
+                        ..
+                    } if name == interface_name => {
+                        if *command == "call" {
+                            methods.iter().map(|method| method.name.clone()).collect()
+                        } else {
+                            properties
+                                .iter()
+                                .map(|property| property.name.clone())
+                                .collect()
+                        }
+                    }
+                    _ => Vec::new(),
+                })
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Bus names, unique ones only after a colon, or child paths of the selected object.
+    fn paths(&self, word: &str) -> Vec<String> {
+        if self.location.bus_name.is_none() || is_bus_name(word) {
+            return bus_names(self.client, word);
+        }
+
+        let dir = word.rfind('/').map(|index| &word[..=index]).unwrap_or("");
+
+        self.with_entries(&resolve(&self.location.path, dir), |entry| match entry {
+            Entry::Node { name } => vec![format!("{}{}/", dir, name)],
+            _ => Vec::new(),
+        })
+    }
+
+    /// Maps the introspected entries of `path` on the selected bus, nothing on errors.
+    fn with_entries(&self, path: &str, f: impl FnMut(&Entry) -> Vec<String>) -> Vec<String> {
+        let bus_name = match &self.location.bus_name {
+            Some(bus_name) => bus_name.clone(),
+            None => return Vec::new(),
+        };
+        let mut entries = self.entries.borrow_mut();
+        let entries = entries
+            .entry((bus_name.clone(), path.to_string()))
+            .or_insert_with(|| self.client.introspect(&bus_name, path).unwrap_or_default());
+
+        entries.iter().flat_map(f).collect()
+    }
+}
+
+impl Completer for ShellHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let mut words = split_words(&line[..pos]);
+        let (start, word) = match words.last() {
+            Some((start, word)) if start + word.len() == pos => {
+                let last = (*start, *word);
+
+                words.pop();
+                last
+            }
+            _ => (pos, ""),
+        };
+        let words = words.into_iter().map(|(_, word)| word).collect_vec();
+
+        Ok((
+            start,
+            self.candidates(&words, word)
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(word))
+                .sorted()
+                .dedup()
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for ShellHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper<'_> {}
+
+impl Validator for ShellHelper<'_> {}
+
+impl Helper for ShellHelper<'_> {}
+
+#[cfg(test)]
+mod test {
+    use dbus_client::DBusError;
+
+    use super::{split_words, Location};
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("call  org.test.Iface Set (\"a b\", [1u, 2u]) '(x'  <s hi>"),
+            vec![
+                (0, "call"),
+                (6, "org.test.Iface"),
+                (21, "Set"),
+                (25, "(\"a b\", [1u, 2u])"),
+                (43, "'(x'"),
+                (49, "<s hi>"),
+            ]
+        );
+        assert_eq!(split_words("cd "), vec![(0, "cd")]);
+    }
+
+    #[test]
+    fn test_cd() {
+        let root = Location::default();
+
+        assert!(matches!(root.cd("/org"), Err(DBusError::Usage(_))));
+
+        let location = root.cd("org.test").unwrap();
+
+        assert_eq!(location.to_string(), "org.test:/");
+        assert_eq!(
+            location.cd("org/test").unwrap().to_string(),
+            "org.test:/org/test"
+        );
+        assert_eq!(
+            location.cd("/org/test/../x/./").unwrap().cd("..").unwrap(),
+            Location {
+                bus_name: Some("org.test".into()),
+                path: "/org".into(),
+            }
+        );
+        assert_eq!(location.cd(":1.42").unwrap().to_string(), ":1.42:/");
+        assert!(matches!(
+            location.cd("org-test"),
+            Err(DBusError::InvalidName(_))
+        ));
+    }
+}